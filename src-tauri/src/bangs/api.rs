@@ -1,19 +1,34 @@
-use crate::bangs::models::Bang;
+use crate::bangs::browsers::{self, BrowserProfile};
+use crate::bangs::launchers::{self, Launcher};
+use crate::bangs::models::{Bang, BangPosition, BangQuery, KeywordConfig};
+use crate::bangs::opensearch;
 use crate::bangs::parser::fetch_duckduckgo_bangs;
-use crate::bangs::storage::{delete_cache, load_cache, load_user_bangs, save_cache};
+use crate::bangs::policies::{self, PolicyExport, PolicyFormat};
+use crate::bangs::revisions::{self, RevisionAction, RevisionDiff, RevisionSummary};
+use crate::bangs::storage::{
+    delete_cache, load_cache, load_keyword_config, load_user_bangs, read_keyword_config,
+    read_user_bangs, save_cache, save_keyword_config,
+};
+use crate::bangs::transfer::{self, BangFormat, ImportConflict, ImportPreview, MergeStrategy};
+use crate::connectivity;
 use crate::logger;
+use crate::persist;
 use chrono::{Duration, Utc};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
 use tauri::AppHandle;
 
-pub async fn load_all_bangs(app_handle: &AppHandle) -> HashMap<String, Bang> {
+pub async fn load_all_bangs(app_handle: &AppHandle, cache_ttl: Duration) -> HashMap<String, Bang> {
     let mut should_update = false;
     let mut bangs = if let Some(cache) = load_cache(app_handle) {
-        // Check if cache is older than 7 days
         let now = Utc::now();
-        if now - cache.last_updated > Duration::days(7) {
+        if now - cache.last_updated > cache_ttl {
             should_update = true;
-            logger::info("Bang cache is older than 7 days, will attempt to update");
+            logger::info(&format!(
+                "Bang cache is older than {} days, will attempt to update",
+                cache_ttl.num_days()
+            ));
         } else {
             logger::info(&format!(
                 "Using bang cache with {} entries (last updated: {})",
@@ -28,7 +43,12 @@ pub async fn load_all_bangs(app_handle: &AppHandle) -> HashMap<String, Bang> {
         HashMap::new()
     };
 
-    if should_update {
+    if should_update && !connectivity::check_online().await {
+        logger::info(&format!(
+            "Offline, skipping bang update and using {} cached bangs",
+            bangs.len()
+        ));
+    } else if should_update {
         match fetch_duckduckgo_bangs().await {
             Ok(fetched_bangs) => {
                 logger::info(&format!(
@@ -95,6 +115,125 @@ pub fn get_bang_url(bangs: &HashMap<String, Bang>, bang_id: &str, query: &str) -
     })
 }
 
+/// Returns the bangs whose trigger starts with the prefix, shortest triggers first
+pub fn complete_bang(
+    bangs: &HashMap<String, Bang>,
+    prefix: &str,
+    limit: usize,
+) -> Vec<(String, String)> {
+    let mut matches: Vec<(String, String)> = bangs
+        .iter()
+        .filter(|(id, _)| id.starts_with(prefix))
+        .map(|(id, bang)| (id.clone(), bang.name.clone()))
+        .collect();
+
+    matches.sort_by(|a, b| a.0.len().cmp(&b.0.len()).then_with(|| a.0.cmp(&b.0)));
+    matches.truncate(limit);
+    matches
+}
+
+/// Finds the first known `!bang` token in the query and splits it from the search term
+pub fn split_bang(bangs: &HashMap<String, Bang>, query: &str) -> Option<BangQuery> {
+    let tokens: Vec<&str> = query.split_whitespace().collect();
+
+    let (index, bang) = tokens.iter().enumerate().find_map(|(index, token)| {
+        token
+            .strip_prefix('!')
+            .filter(|bang| bangs.contains_key(*bang))
+            .map(|bang| (index, bang))
+    })?;
+
+    let term = tokens
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != index)
+        .map(|(_, token)| *token)
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Some(BangQuery {
+        bang: bang.to_string(),
+        term,
+        position: if index == 0 {
+            BangPosition::BeforeTerm
+        } else {
+            BangPosition::AfterTerm
+        },
+    })
+}
+
+/// Resolves a query with a known bang before or after the search term, e.g. `!w rust`
+/// or `rust !w`
+pub fn get_query_bang_url(bangs: &HashMap<String, Bang>, query: &str) -> Option<String> {
+    let bang_query = split_bang(bangs, query)?;
+    get_bang_url(bangs, &bang_query.bang, &bang_query.term)
+}
+
+/// Splits a query whose first word is an enabled keyword of a known bang, e.g. `gh tauri`
+pub fn split_keyword(
+    bangs: &HashMap<String, Bang>,
+    keywords: &KeywordConfig,
+    query: &str,
+) -> Option<BangQuery> {
+    if !keywords.enabled {
+        return None;
+    }
+
+    let query = query.trim();
+    let (keyword, term) = query.split_once(char::is_whitespace).unwrap_or((query, ""));
+
+    if !keywords.keywords.iter().any(|k| k == keyword) || !bangs.contains_key(keyword) {
+        return None;
+    }
+
+    Some(BangQuery {
+        bang: keyword.to_string(),
+        term: term.trim().to_string(),
+        position: BangPosition::Keyword,
+    })
+}
+
+/// Resolves a query whose first word is an enabled keyword, e.g. `gh tauri`.
+/// A keyword on its own opens the bang's home page.
+pub fn get_keyword_url(
+    bangs: &HashMap<String, Bang>,
+    keywords: &KeywordConfig,
+    query: &str,
+) -> Option<String> {
+    let keyword_query = split_keyword(bangs, keywords, query)?;
+    if keyword_query.term.is_empty() {
+        return bangs
+            .get(&keyword_query.bang)
+            .map(|bang| bang.home_url.clone());
+    }
+
+    get_bang_url(bangs, &keyword_query.bang, &keyword_query.term)
+}
+
+pub fn load_keywords(app_handle: &AppHandle) -> KeywordConfig {
+    load_keyword_config(app_handle)
+}
+
+/// Saves the keyword config after checking every keyword is a known bang
+pub fn update_keyword_config(
+    app_handle: &AppHandle,
+    all_bangs: &HashMap<String, Bang>,
+    keywords: &mut KeywordConfig,
+    config: KeywordConfig,
+) -> Result<(), String> {
+    if let Some(unknown) = config
+        .keywords
+        .iter()
+        .find(|id| !all_bangs.contains_key(*id))
+    {
+        return Err(format!("Bang not found: {}", unknown));
+    }
+
+    save_keyword_config(app_handle, &config)?;
+    *keywords = config;
+    Ok(())
+}
+
 pub fn add_custom_bang(
     app_handle: &AppHandle,
     all_bangs: &mut HashMap<String, Bang>,
@@ -105,10 +244,14 @@ pub fn add_custom_bang(
     let mut custom_bang = bang;
     custom_bang.is_custom = true;
 
-    user_bangs.insert(custom_bang.id.clone(), custom_bang.clone());
-    all_bangs.insert(custom_bang.id.clone(), custom_bang);
+    let action = if user_bangs.contains_key(&custom_bang.id) {
+        RevisionAction::Update
+    } else {
+        RevisionAction::Add
+    };
 
-    save_user_bangs(app_handle, &user_bangs)
+    user_bangs.insert(custom_bang.id.clone(), custom_bang);
+    commit_user_bangs(app_handle, all_bangs, user_bangs, action)
 }
 
 pub fn delete_custom_bang(
@@ -127,9 +270,405 @@ pub fn delete_custom_bang(
     }
 
     user_bangs.remove(bang_id);
-    all_bangs.remove(bang_id);
+    commit_user_bangs(app_handle, all_bangs, user_bangs, RevisionAction::Delete)
+}
+
+/// Checks a user bang before it replaces the ones in use
+fn validate_user_bang(id: &str, bang: &Bang) -> Result<(), String> {
+    if id.is_empty() || id.contains(char::is_whitespace) {
+        return Err(format!("Invalid bang trigger: '{}'", id));
+    }
 
-    save_user_bangs(app_handle, &user_bangs)
+    if bang.name.trim().is_empty() {
+        return Err(format!("Bang '{}' has no name", id));
+    }
+
+    let example_url = bang
+        .search_url
+        .replace("{{{s}}}", "test")
+        .replace("{{{qe}}}", "test")
+        .replace("{{qe}}", "test")
+        .replace("{{q}}", "test");
+    url::Url::parse(&example_url)
+        .map_err(|e| format!("Invalid search URL for bang '{}': {}", id, e))?;
+
+    Ok(())
+}
+
+/// Re-reads the user bangs file and swaps the custom bangs in use for its contents.
+/// Nothing changes when the file can't be read or contains an invalid bang.
+pub fn reload_user_bangs(
+    app_handle: &AppHandle,
+    all_bangs: &mut HashMap<String, Bang>,
+) -> Result<usize, String> {
+    let mut user_bangs = read_user_bangs(app_handle)?;
+    for (id, bang) in user_bangs.iter_mut() {
+        validate_user_bang(id, bang)?;
+        bang.is_custom = true;
+    }
+
+    let previous = custom_bangs(all_bangs);
+    revisions::record_if_changed(&previous, &user_bangs);
+
+    let count = user_bangs.len();
+    replace_user_bangs(app_handle, all_bangs, user_bangs);
+    Ok(count)
+}
+
+fn custom_bangs(all_bangs: &HashMap<String, Bang>) -> HashMap<String, Bang> {
+    all_bangs
+        .iter()
+        .filter(|(_, bang)| bang.is_custom)
+        .map(|(id, bang)| (id.clone(), bang.clone()))
+        .collect()
+}
+
+/// Swaps the custom bangs in use for the given ones
+fn replace_user_bangs(
+    app_handle: &AppHandle,
+    all_bangs: &mut HashMap<String, Bang>,
+    user_bangs: HashMap<String, Bang>,
+) {
+    let removed: Vec<String> = all_bangs
+        .iter()
+        .filter(|(id, bang)| bang.is_custom && !user_bangs.contains_key(*id))
+        .map(|(id, _)| id.clone())
+        .collect();
+
+    if !removed.is_empty() {
+        // Custom bangs can shadow built-in ones, which come back once the custom one is gone
+        let mut built_in = load_cache(app_handle)
+            .map(|cache| cache.bangs)
+            .unwrap_or_default();
+        for id in removed {
+            match built_in.remove(&id) {
+                Some(bang) => all_bangs.insert(id, bang),
+                None => all_bangs.remove(&id),
+            };
+        }
+    }
+
+    for (id, mut bang) in user_bangs {
+        bang.is_custom = true;
+        all_bangs.insert(id, bang);
+    }
+}
+
+/// Saves the user bangs and puts them in use, recording the change as a revision
+fn commit_user_bangs(
+    app_handle: &AppHandle,
+    all_bangs: &mut HashMap<String, Bang>,
+    user_bangs: HashMap<String, Bang>,
+    action: RevisionAction,
+) -> Result<(), String> {
+    let previous = load_user_bangs(app_handle);
+    save_user_bangs(app_handle, &user_bangs)?;
+
+    let bang_ids = revisions::diff(&previous, &user_bangs).bang_ids();
+    revisions::record(&previous, &user_bangs, action, bang_ids);
+
+    replace_user_bangs(app_handle, all_bangs, user_bangs);
+    Ok(())
+}
+
+pub fn list_bang_revisions() -> Vec<RevisionSummary> {
+    revisions::list_revisions()
+}
+
+pub fn diff_bang_revisions(from_id: u64, to_id: u64) -> Result<RevisionDiff, String> {
+    revisions::diff_revisions(from_id, to_id)
+}
+
+/// Brings the user bangs back to how they were in an earlier revision
+pub fn restore_bang_revision(
+    app_handle: &AppHandle,
+    all_bangs: &mut HashMap<String, Bang>,
+    revision_id: u64,
+) -> Result<(), String> {
+    let revision = revisions::get_revision(revision_id)?;
+    logger::info(&format!(
+        "Restoring custom bangs from revision {}",
+        revision_id
+    ));
+    commit_user_bangs(
+        app_handle,
+        all_bangs,
+        revision.bangs,
+        RevisionAction::Restore,
+    )
+}
+
+/// Reverts the last delete, update, import or restore if it was made moments ago
+pub fn undo_bang_change(
+    app_handle: &AppHandle,
+    all_bangs: &mut HashMap<String, Bang>,
+) -> Result<(), String> {
+    let user_bangs = revisions::take_undo()?;
+    logger::info("Undoing the last change to custom bangs");
+    commit_user_bangs(app_handle, all_bangs, user_bangs, RevisionAction::Undo)
+}
+
+/// Re-reads the keyword config, keeping the current one when the file is invalid
+pub fn reload_keywords(app_handle: &AppHandle) -> Result<KeywordConfig, String> {
+    read_keyword_config(app_handle)
+}
+
+/// Writes the custom bangs to a file, returning how many were exported
+pub fn export_bangs(
+    app_handle: &AppHandle,
+    path: &Path,
+    format: BangFormat,
+) -> Result<usize, String> {
+    let user_bangs = load_user_bangs(app_handle);
+    let content = transfer::format_bangs(&user_bangs, format)?;
+    persist::write_file(path, content.as_bytes(), 0)?;
+
+    logger::info(&format!("Exported {} custom bangs", user_bangs.len()));
+    Ok(user_bangs.len())
+}
+
+/// Writes bangs as browser search engines: a Firefox `policies.json`, a Chromium
+/// `ManagedSearchEngines` policy, or an OpenSearch description per bang into the
+/// directory at `path`. Without `bang_ids`, the custom bangs are exported.
+pub fn export_browser_policies(
+    all_bangs: &HashMap<String, Bang>,
+    bang_ids: &[String],
+    format: PolicyFormat,
+    path: &Path,
+) -> Result<PolicyExport, String> {
+    let mut export = PolicyExport::default();
+
+    let mut selected: Vec<&Bang> = if bang_ids.is_empty() {
+        all_bangs.values().filter(|bang| bang.is_custom).collect()
+    } else {
+        bang_ids
+            .iter()
+            .filter_map(|id| {
+                let bang = all_bangs.get(id);
+                if bang.is_none() {
+                    export.unsupported.push(format!("Bang not found: {}", id));
+                }
+                bang
+            })
+            .collect()
+    };
+    selected.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let (engines, unsupported) = policies::to_search_engines(&selected);
+    export.unsupported.extend(unsupported);
+    if engines.is_empty() {
+        return Err("None of the bangs can be used as browser search engines".to_string());
+    }
+
+    match format {
+        PolicyFormat::Firefox => {
+            let content = policies::format_firefox_policies(&engines)?;
+            persist::write_file(path, content.as_bytes(), 0)?;
+            export.exported = engines
+                .iter()
+                .map(|engine| engine.bang.id.clone())
+                .collect();
+        }
+        PolicyFormat::Chromium => {
+            let content = policies::format_chromium_policy(&engines)?;
+            persist::write_file(path, content.as_bytes(), 0)?;
+            export.exported = engines
+                .iter()
+                .map(|engine| engine.bang.id.clone())
+                .collect();
+        }
+        PolicyFormat::OpenSearch => {
+            let mut file_names = HashSet::new();
+            for engine in &engines {
+                let file_name = policies::opensearch_file_name(engine.bang);
+                // Triggers that only differ in characters replaced in file names
+                if !file_names.insert(file_name.clone()) {
+                    export.unsupported.push(format!(
+                        "{} ({}): {} is already used by another bang",
+                        engine.bang.name, engine.bang.id, file_name
+                    ));
+                    continue;
+                }
+
+                let content = policies::format_opensearch(engine);
+                persist::write_file(&path.join(&file_name), content.as_bytes(), 0)?;
+                export.exported.push(engine.bang.id.clone());
+            }
+        }
+    }
+
+    logger::info(&format!(
+        "Exported {} bangs as browser search engines, {} couldn't be",
+        export.exported.len(),
+        export.unsupported.len()
+    ));
+    Ok(export)
+}
+
+/// Reads bangs from a file and merges them into the custom bangs, unless it's a dry run.
+/// The format is guessed from the file when not given.
+pub fn import_bangs(
+    app_handle: &AppHandle,
+    all_bangs: &mut HashMap<String, Bang>,
+    path: &Path,
+    format: Option<BangFormat>,
+    strategy: MergeStrategy,
+    dry_run: bool,
+) -> Result<ImportPreview, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let format = match format {
+        Some(format) => format,
+        None => BangFormat::detect(path, &content)?,
+    };
+
+    let imported = transfer::parse_bangs(&content, format)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    merge_imported_bangs(app_handle, all_bangs, imported, strategy, dry_run)
+}
+
+pub fn find_browser_profiles() -> Vec<BrowserProfile> {
+    browsers::find_browser_profiles()
+}
+
+/// Imports the keyword search engines of a Chromium or Firefox profile as custom bangs,
+/// unless it's a dry run
+pub fn import_browser_bangs(
+    app_handle: &AppHandle,
+    all_bangs: &mut HashMap<String, Bang>,
+    path: &Path,
+    strategy: MergeStrategy,
+    dry_run: bool,
+) -> Result<ImportPreview, String> {
+    let import = browsers::read_browser_engines(path)?;
+    let mut preview = merge_imported_bangs(app_handle, all_bangs, import.bangs, strategy, dry_run)?;
+    preview.unsupported = import.unsupported;
+    Ok(preview)
+}
+
+/// Imports the web search shortcuts of Alfred, Raycast or Flow Launcher as custom bangs,
+/// unless it's a dry run
+pub fn import_launcher_bangs(
+    app_handle: &AppHandle,
+    all_bangs: &mut HashMap<String, Bang>,
+    path: &Path,
+    launcher: Option<Launcher>,
+    strategy: MergeStrategy,
+    dry_run: bool,
+) -> Result<ImportPreview, String> {
+    let import = launchers::read_launcher_searches(path, launcher)?;
+    let mut preview = merge_imported_bangs(app_handle, all_bangs, import.bangs, strategy, dry_run)?;
+    preview.unsupported = import.unsupported;
+    Ok(preview)
+}
+
+/// Makes a custom bang from an OpenSearch description without saving it, so it can be
+/// reviewed first. A trigger that's already taken gets a number added, e.g. `github2`.
+pub async fn bang_from_opensearch(
+    url_or_file: &str,
+    is_taken: impl Fn(&str) -> bool,
+) -> Result<Bang, String> {
+    let mut bang = opensearch::fetch_opensearch_bang(url_or_file).await?;
+    if is_taken(&bang.id) {
+        bang.id = transfer::free_trigger(&bang.id, &is_taken);
+    }
+
+    validate_user_bang(&bang.id, &bang)?;
+    Ok(bang)
+}
+
+/// Compares imported bangs with the ones in use and, unless it's a dry run, adds them
+/// to the custom bangs as a single revision
+pub(crate) fn merge_imported_bangs(
+    app_handle: &AppHandle,
+    all_bangs: &mut HashMap<String, Bang>,
+    imported: Vec<Bang>,
+    strategy: MergeStrategy,
+    dry_run: bool,
+) -> Result<ImportPreview, String> {
+    let mut user_bangs = load_user_bangs(app_handle);
+    let (preview, changed) = merge_bangs(&mut user_bangs, all_bangs, imported, strategy);
+
+    logger::info(&format!(
+        "Import{}: {} new, {} conflicting, {} identical, {} invalid bangs",
+        if dry_run { " preview" } else { "" },
+        preview.new.len(),
+        preview.conflicting.len(),
+        preview.identical.len(),
+        preview.invalid.len()
+    ));
+
+    if !dry_run && changed {
+        commit_user_bangs(app_handle, all_bangs, user_bangs, RevisionAction::Import)?;
+    }
+
+    Ok(preview)
+}
+
+/// Adds the imported bangs to the user bangs, returning what happened to each of them
+/// and whether the user bangs changed
+fn merge_bangs(
+    user_bangs: &mut HashMap<String, Bang>,
+    all_bangs: &HashMap<String, Bang>,
+    mut imported: Vec<Bang>,
+    strategy: MergeStrategy,
+) -> (ImportPreview, bool) {
+    imported.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let mut preview = ImportPreview::default();
+    let mut changed = false;
+
+    for bang in imported {
+        if let Err(e) = validate_user_bang(&bang.id, &bang) {
+            preview.invalid.push(e);
+            continue;
+        }
+
+        // Earlier bangs from the same import count as existing ones
+        let existing = user_bangs
+            .get(&bang.id)
+            .or_else(|| all_bangs.get(&bang.id))
+            .cloned();
+
+        match existing {
+            None => {
+                preview.new.push(bang.clone());
+                user_bangs.insert(bang.id.clone(), bang);
+                changed = true;
+            }
+            Some(existing) if transfer::is_same_bang(&existing, &bang) => {
+                preview.identical.push(bang.id);
+            }
+            Some(existing) => {
+                let mut conflict = ImportConflict {
+                    existing_builtin: !user_bangs.contains_key(&bang.id),
+                    existing,
+                    imported: bang.clone(),
+                    renamed_to: None,
+                };
+
+                match strategy {
+                    MergeStrategy::Skip => {}
+                    MergeStrategy::Overwrite => {
+                        user_bangs.insert(bang.id.clone(), bang);
+                        changed = true;
+                    }
+                    MergeStrategy::Rename => {
+                        let id = transfer::free_trigger(&bang.id, |candidate| {
+                            user_bangs.contains_key(candidate) || all_bangs.contains_key(candidate)
+                        });
+                        conflict.renamed_to = Some(id.clone());
+                        user_bangs.insert(id.clone(), Bang { id, ..bang });
+                        changed = true;
+                    }
+                }
+
+                preview.conflicting.push(conflict);
+            }
+        }
+    }
+
+    (preview, changed)
 }
 
 pub fn get_all_bangs(bangs: &HashMap<String, Bang>) -> Vec<(String, String)> {
@@ -144,6 +683,11 @@ pub fn clear_cache(app_handle: &AppHandle) -> Result<(), String> {
 }
 
 pub async fn refresh_bangs(app_handle: &AppHandle) -> Result<HashMap<String, Bang>, String> {
+    // Keep the cache around when there's no way to fetch a new one
+    if !connectivity::is_online() {
+        return Err("Can't refresh bangs while offline".to_string());
+    }
+
     clear_cache(app_handle)?;
 
     match fetch_duckduckgo_bangs().await {
@@ -174,3 +718,112 @@ pub fn save_user_bangs(
 ) -> Result<(), String> {
     crate::bangs::storage::save_user_bangs(app_handle, bangs)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bangs() -> HashMap<String, Bang> {
+        let bang = Bang {
+            id: "w".to_string(),
+            name: "Wikipedia".to_string(),
+            search_url: "https://en.wikipedia.org/wiki/Special:Search?search={{{s}}}".to_string(),
+            home_url: "https://en.wikipedia.org".to_string(),
+            category: "Research".to_string(),
+            is_custom: false,
+            suggest_url: None,
+            icon_url: None,
+        };
+        HashMap::from([(bang.id.clone(), bang)])
+    }
+
+    #[test]
+    fn resolves_bang_before_and_after_the_term() {
+        let bangs = bangs();
+        let expected = "https://en.wikipedia.org/wiki/Special:Search?search=rust%20lang";
+
+        assert_eq!(
+            get_query_bang_url(&bangs, "!w rust lang").as_deref(),
+            Some(expected)
+        );
+        assert_eq!(
+            get_query_bang_url(&bangs, "rust lang !w").as_deref(),
+            Some(expected)
+        );
+    }
+
+    #[test]
+    fn splits_enabled_keywords_of_known_bangs() {
+        let bangs = bangs();
+        let mut keywords = KeywordConfig {
+            enabled: true,
+            keywords: vec!["w".to_string(), "gone".to_string()],
+        };
+
+        let keyword_query = split_keyword(&bangs, &keywords, "w  rust lang").unwrap();
+        assert_eq!(keyword_query.bang, "w");
+        assert_eq!(keyword_query.term, "rust lang");
+        assert_eq!(keyword_query.with_term("rust book"), "w rust book");
+
+        assert!(split_keyword(&bangs, &keywords, "gone rust").is_none());
+        assert!(split_keyword(&bangs, &keywords, "rust w").is_none());
+        assert_eq!(
+            get_keyword_url(&bangs, &keywords, "w").as_deref(),
+            Some("https://en.wikipedia.org")
+        );
+
+        keywords.enabled = false;
+        assert!(split_keyword(&bangs, &keywords, "w rust").is_none());
+    }
+
+    #[test]
+    fn reports_renamed_triggers_and_builtin_conflicts() {
+        let builtin = bangs();
+        let mut user_bangs = HashMap::new();
+        let imported = Bang {
+            search_url: "https://example.com/search?q={{{s}}}".to_string(),
+            home_url: "https://example.com".to_string(),
+            is_custom: true,
+            ..builtin["w"].clone()
+        };
+
+        let (preview, changed) = merge_bangs(
+            &mut user_bangs,
+            &builtin,
+            vec![imported.clone()],
+            MergeStrategy::Rename,
+        );
+
+        assert!(changed);
+        let conflict = &preview.conflicting[0];
+        assert!(conflict.existing_builtin);
+        assert_eq!(conflict.renamed_to.as_deref(), Some("w2"));
+        assert!(user_bangs.contains_key("w2"));
+
+        // The renamed bang is now the user's own, so it doesn't count as built-in
+        let (preview, _) = merge_bangs(
+            &mut user_bangs,
+            &builtin,
+            vec![Bang {
+                id: "w2".to_string(),
+                name: "Other".to_string(),
+                ..imported
+            }],
+            MergeStrategy::Skip,
+        );
+        assert!(!preview.conflicting[0].existing_builtin);
+        assert_eq!(preview.conflicting[0].renamed_to, None);
+    }
+
+    #[test]
+    fn ignores_unknown_bangs_and_exclamation_marks_in_the_term() {
+        let bangs = bangs();
+
+        assert_eq!(get_query_bang_url(&bangs, "!nope rust"), None);
+        assert_eq!(get_query_bang_url(&bangs, "hello!w"), None);
+        assert_eq!(
+            get_query_bang_url(&bangs, "wow! !w").as_deref(),
+            Some("https://en.wikipedia.org/wiki/Special:Search?search=wow%21")
+        );
+    }
+}
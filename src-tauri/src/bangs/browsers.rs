@@ -0,0 +1,370 @@
+use crate::bangs::transfer::ImportedBangs;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Chromium keeps its search engines in this SQLite database in every profile
+const CHROMIUM_DATABASE: &str = "Web Data";
+
+/// Firefox's search engines, LZ4-compressed JSON
+const FIREFOX_SEARCH_FILE: &str = "search.json.mozlz4";
+
+/// Header Firefox puts in front of its LZ4 block
+const MOZLZ4_MAGIC: &[u8] = b"mozLz40\0";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Browser {
+    Chrome,
+    Edge,
+    Brave,
+    Firefox,
+}
+
+impl Browser {
+    fn name(self) -> &'static str {
+        match self {
+            Browser::Chrome => "Chrome",
+            Browser::Edge => "Edge",
+            Browser::Brave => "Brave",
+            Browser::Firefox => "Firefox",
+        }
+    }
+
+    /// Directory holding the browser's profiles
+    fn profiles_dir(self) -> Option<PathBuf> {
+        let (base, path) = if cfg!(windows) {
+            match self {
+                Browser::Chrome => (dirs::data_local_dir(), "Google/Chrome/User Data"),
+                Browser::Edge => (dirs::data_local_dir(), "Microsoft/Edge/User Data"),
+                Browser::Brave => (
+                    dirs::data_local_dir(),
+                    "BraveSoftware/Brave-Browser/User Data",
+                ),
+                Browser::Firefox => (dirs::config_dir(), "Mozilla/Firefox/Profiles"),
+            }
+        } else if cfg!(target_os = "macos") {
+            match self {
+                Browser::Chrome => (dirs::config_dir(), "Google/Chrome"),
+                Browser::Edge => (dirs::config_dir(), "Microsoft Edge"),
+                Browser::Brave => (dirs::config_dir(), "BraveSoftware/Brave-Browser"),
+                Browser::Firefox => (dirs::config_dir(), "Firefox/Profiles"),
+            }
+        } else {
+            match self {
+                Browser::Chrome => (dirs::config_dir(), "google-chrome"),
+                Browser::Edge => (dirs::config_dir(), "microsoft-edge"),
+                Browser::Brave => (dirs::config_dir(), "BraveSoftware/Brave-Browser"),
+                Browser::Firefox => (dirs::home_dir(), ".mozilla/firefox"),
+            }
+        };
+
+        base.map(|base| base.join(path))
+    }
+
+    /// The file with the search engines inside a profile directory
+    fn search_file(self) -> &'static str {
+        match self {
+            Browser::Firefox => FIREFOX_SEARCH_FILE,
+            _ => CHROMIUM_DATABASE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BrowserProfile {
+    pub browser: Browser,
+    /// Name of the profile directory, e.g. `Default` or `Profile 1`
+    pub name: String,
+    /// The file the search engines are read from
+    pub path: PathBuf,
+}
+
+/// Profiles of the installed browsers that have search engines to import
+pub fn find_browser_profiles() -> Vec<BrowserProfile> {
+    let mut profiles = Vec::new();
+
+    for browser in [
+        Browser::Chrome,
+        Browser::Edge,
+        Browser::Brave,
+        Browser::Firefox,
+    ] {
+        let Some(entries) = browser
+            .profiles_dir()
+            .and_then(|dir| fs::read_dir(dir).ok())
+        else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path().join(browser.search_file());
+            if path.is_file() {
+                profiles.push(BrowserProfile {
+                    browser,
+                    name: entry.file_name().to_string_lossy().to_string(),
+                    path,
+                });
+            }
+        }
+    }
+
+    profiles.sort_by(|a, b| (a.browser.name(), &a.name).cmp(&(b.browser.name(), &b.name)));
+    profiles
+}
+
+/// Reads the search engines of a browser profile, from either the profile directory
+/// or its `Web Data`/`search.json.mozlz4` file
+pub fn read_browser_engines(path: &Path) -> Result<ImportedBangs, String> {
+    let path = if path.is_dir() {
+        [CHROMIUM_DATABASE, FIREFOX_SEARCH_FILE]
+            .iter()
+            .map(|file| path.join(file))
+            .find(|path| path.is_file())
+            .ok_or_else(|| format!("No search engines found in {}", path.display()))?
+    } else {
+        path.to_path_buf()
+    };
+
+    let is_firefox = path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().ends_with(".mozlz4"));
+
+    if is_firefox {
+        read_firefox_engines(&path)
+    } else {
+        read_chromium_engines(&path, browser_of(&path))
+    }
+}
+
+/// Which Chromium browser a profile belongs to, going by its path
+fn browser_of(path: &Path) -> Browser {
+    let path = path.to_string_lossy().to_lowercase();
+    if path.contains("edge") {
+        Browser::Edge
+    } else if path.contains("brave") {
+        Browser::Brave
+    } else {
+        Browser::Chrome
+    }
+}
+
+fn read_chromium_engines(path: &Path, browser: Browser) -> Result<ImportedBangs, String> {
+    // The database is locked while the browser runs, so a copy is read instead. Recent
+    // changes may still be in the write-ahead log or rollback journal, which are copied
+    // along with it under the same name so SQLite applies them.
+    let copy_dir = tempfile::tempdir()
+        .map_err(|e| format!("Failed to create a temporary directory: {}", e))?;
+    let file_name = path.file_name().unwrap_or(OsStr::new(CHROMIUM_DATABASE));
+    let copy_path = copy_dir.path().join(file_name);
+    fs::copy(path, &copy_path).map_err(|e| format!("Failed to copy {}: {}", path.display(), e))?;
+
+    for suffix in ["-wal", "-journal"] {
+        let mut sidecar_name = file_name.to_os_string();
+        sidecar_name.push(suffix);
+        let sidecar = path.with_file_name(&sidecar_name);
+        if sidecar.exists() {
+            fs::copy(&sidecar, copy_dir.path().join(&sidecar_name))
+                .map_err(|e| format!("Failed to copy {}: {}", sidecar.display(), e))?;
+        }
+    }
+
+    query_chromium_engines(&copy_path, browser)
+}
+
+fn query_chromium_engines(path: &Path, browser: Browser) -> Result<ImportedBangs, String> {
+    // Opened for writing so SQLite can replay the copied log, only the copy is changed
+    let connection = Connection::open(path)
+        .map_err(|e| format!("Failed to open {} search engines: {}", browser.name(), e))?;
+
+    // Engines that come with the browser or were picked up from visited sites are skipped,
+    // only the ones the user added or edited are imported
+    let mut statement = connection
+        .prepare(
+            "SELECT short_name, keyword, url, suggest_url FROM keywords
+             WHERE prepopulate_id = 0 AND safe_for_autoreplace = 0",
+        )
+        .map_err(|e| format!("Failed to read {} search engines: {}", browser.name(), e))?;
+
+    let rows = statement
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+            ))
+        })
+        .map_err(|e| format!("Failed to read {} search engines: {}", browser.name(), e))?;
+
+    let mut import = ImportedBangs::default();
+    for row in rows {
+        let (name, keyword, url, suggest_url) =
+            row.map_err(|e| format!("Failed to read {} search engines: {}", browser.name(), e))?;
+        import.add(
+            browser.name(),
+            &keyword,
+            &name,
+            &url,
+            suggest_url.as_deref(),
+        );
+    }
+
+    Ok(import)
+}
+
+#[derive(Deserialize)]
+struct FirefoxSearch {
+    #[serde(default)]
+    engines: Vec<FirefoxEngine>,
+}
+
+#[derive(Deserialize)]
+struct FirefoxEngine {
+    #[serde(rename = "_name", default)]
+    name: String,
+    #[serde(rename = "_metaData", default)]
+    metadata: FirefoxMetadata,
+    #[serde(rename = "_definedAliases", default)]
+    defined_aliases: Vec<String>,
+    #[serde(rename = "_urls", default)]
+    urls: Vec<FirefoxUrl>,
+}
+
+#[derive(Default, Deserialize)]
+struct FirefoxMetadata {
+    /// Keyword set by the user
+    alias: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct FirefoxUrl {
+    template: String,
+    #[serde(rename = "type", default)]
+    kind: Option<String>,
+    #[serde(default)]
+    params: Vec<FirefoxParam>,
+}
+
+#[derive(Deserialize)]
+struct FirefoxParam {
+    name: String,
+    value: Option<String>,
+}
+
+impl FirefoxUrl {
+    /// The template with its parameters added to the query string
+    fn full_template(&self) -> String {
+        let params: Vec<String> = self
+            .params
+            .iter()
+            .filter_map(|param| {
+                let value = param.value.as_deref()?;
+                // Keep placeholders readable for the template conversion
+                let value = value
+                    .split("{searchTerms}")
+                    .map(|part| urlencoding::encode(part).into_owned())
+                    .collect::<Vec<_>>()
+                    .join("{searchTerms}");
+                Some(format!("{}={}", urlencoding::encode(&param.name), value))
+            })
+            .collect();
+
+        if params.is_empty() {
+            return self.template.clone();
+        }
+
+        let separator = if self.template.contains('?') {
+            '&'
+        } else {
+            '?'
+        };
+        format!("{}{}{}", self.template, separator, params.join("&"))
+    }
+}
+
+/// Decompresses a `.mozlz4` file: a magic header followed by a size-prefixed LZ4 block
+fn decompress_mozlz4(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let block = bytes
+        .strip_prefix(MOZLZ4_MAGIC)
+        .ok_or_else(|| "Not a mozlz4 file".to_string())?;
+
+    lz4_flex::block::decompress_size_prepended(block).map_err(|e| e.to_string())
+}
+
+fn read_firefox_engines(path: &Path) -> Result<ImportedBangs, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let json = decompress_mozlz4(&bytes)
+        .map_err(|e| format!("Failed to decompress Firefox search engines: {}", e))?;
+    let search = serde_json::from_slice::<FirefoxSearch>(&json)
+        .map_err(|e| format!("Failed to parse Firefox search engines: {}", e))?;
+
+    let mut import = ImportedBangs::default();
+    for engine in search.engines {
+        let url_of_kind = |kind: &str| {
+            engine
+                .urls
+                .iter()
+                .find(|url| url.kind.as_deref().unwrap_or("text/html") == kind)
+                .map(FirefoxUrl::full_template)
+        };
+
+        // Built-in engines keep their URLs outside the profile and are left out
+        let Some(search_url) = url_of_kind("text/html") else {
+            continue;
+        };
+        let suggest_url = url_of_kind("application/x-suggestions+json");
+
+        let keyword = engine
+            .metadata
+            .alias
+            .clone()
+            .or_else(|| engine.defined_aliases.first().cloned())
+            .unwrap_or_default();
+
+        import.add(
+            Browser::Firefox.name(),
+            &keyword,
+            &engine.name,
+            &search_url,
+            suggest_url.as_deref(),
+        );
+    }
+
+    Ok(import)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_engines_still_in_the_write_ahead_log() {
+        let profile = tempfile::tempdir().unwrap();
+        let path = profile.path().join(CHROMIUM_DATABASE);
+
+        // Kept open like a running browser, so the insert stays in the log
+        let browser = Connection::open(&path).unwrap();
+        browser
+            .execute_batch(
+                "PRAGMA journal_mode = WAL;
+                 PRAGMA wal_autocheckpoint = 0;
+                 CREATE TABLE keywords (short_name TEXT, keyword TEXT, url TEXT,
+                     suggest_url TEXT, prepopulate_id INTEGER, safe_for_autoreplace INTEGER);
+                 INSERT INTO keywords VALUES ('Crates', 'crates',
+                     'https://crates.io/search?q={searchTerms}', NULL, 0, 0);
+                 INSERT INTO keywords VALUES ('Google', 'google.com',
+                     'https://www.google.com/search?q={searchTerms}', NULL, 1, 0);",
+            )
+            .unwrap();
+        assert!(profile.path().join("Web Data-wal").exists());
+
+        let import = read_chromium_engines(&path, Browser::Chrome).unwrap();
+
+        let ids: Vec<&str> = import.bangs.iter().map(|bang| bang.id.as_str()).collect();
+        assert_eq!(ids, vec!["crates"]);
+        drop(browser);
+    }
+}
@@ -0,0 +1,519 @@
+use crate::bangs::transfer::{keyword_from_name, ImportedBangs};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where Alfred keeps its custom searches inside `Alfred.alfredpreferences`
+const ALFRED_PREFS_FILE: &str = "preferences/features/websearch/prefs.plist";
+
+/// Launchers whose web search shortcuts can be imported. unduck uses DuckDuckGo's
+/// bangs, so it's covered by the DuckDuckGo bang file import.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Launcher {
+    /// Custom searches, from `Alfred.alfredpreferences` or its websearch `prefs.plist`
+    Alfred,
+    /// Quicklinks exported as JSON
+    Raycast,
+    /// The WebSearch plugin's `Settings.json`, which Wox uses too
+    #[serde(rename = "flow_launcher")]
+    Flow,
+}
+
+impl Launcher {
+    fn name(self) -> &'static str {
+        match self {
+            Launcher::Alfred => "Alfred",
+            Launcher::Raycast => "Raycast",
+            Launcher::Flow => "Flow Launcher",
+        }
+    }
+
+    /// Guesses the launcher from the file extension, telling the JSON files apart by content
+    pub fn detect(path: &Path) -> Result<Self, String> {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+
+        match extension.as_deref() {
+            Some("plist") | Some("alfredpreferences") => Ok(Launcher::Alfred),
+            Some("json") => {
+                let content = fs::read_to_string(path)
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+                if content.contains("\"SearchSources\"") {
+                    Ok(Launcher::Flow)
+                } else {
+                    Ok(Launcher::Raycast)
+                }
+            }
+            _ => Err(format!("Unknown launcher file: {}", path.display())),
+        }
+    }
+}
+
+lazy_static! {
+    /// Raycast's query placeholders: `{Query}`, `{argument}` or `{argument name="..."}`,
+    /// optionally followed by modifiers like `| percent-encode`
+    static ref RAYCAST_ARGUMENT: Regex =
+        Regex::new(r#"(?i)\{\s*(?:query|argument)((?:\s+\w+="[^"]*")*)\s*((?:\|[^}]*)?)\}"#)
+            .unwrap();
+    static ref RAYCAST_ARGUMENT_NAME: Regex = Regex::new(r#"name="([^"]*)""#).unwrap();
+}
+
+/// Reads the web search shortcuts of a launcher, detecting which one when it isn't given
+pub fn read_launcher_searches(
+    path: &Path,
+    launcher: Option<Launcher>,
+) -> Result<ImportedBangs, String> {
+    let launcher = match launcher {
+        Some(launcher) => launcher,
+        None => Launcher::detect(path)?,
+    };
+
+    match launcher {
+        Launcher::Alfred => read_alfred_searches(path),
+        Launcher::Raycast => read_raycast_quicklinks(path),
+        Launcher::Flow => read_flow_launcher_searches(path),
+    }
+}
+
+#[derive(Deserialize)]
+struct AlfredPrefs {
+    #[serde(rename = "customSites", default)]
+    custom_sites: BTreeMap<String, AlfredSite>,
+}
+
+#[derive(Deserialize)]
+struct AlfredSite {
+    #[serde(default)]
+    keyword: String,
+    /// Shown while typing, e.g. `Search Google for '{query}'`
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    url: String,
+    #[serde(default = "enabled")]
+    enabled: bool,
+    /// Whether the query is encoded as UTF-8 rather than the legacy Mac encoding
+    #[serde(default = "enabled")]
+    utf8: bool,
+    /// What spaces in the query are replaced with, instead of encoding them
+    #[serde(default)]
+    spaces: Option<String>,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl AlfredSite {
+    /// The title without the query, e.g. `Search Google`
+    fn name(&self) -> String {
+        let name = self
+            .text
+            .replace("'{query}'", "")
+            .replace("\"{query}\"", "")
+            .replace("{query}", "");
+        let name = name.trim().trim_end_matches(" for").trim();
+
+        if name.is_empty() {
+            self.keyword.clone()
+        } else {
+            name.to_string()
+        }
+    }
+}
+
+fn read_alfred_searches(path: &Path) -> Result<ImportedBangs, String> {
+    let path = if path.is_dir() {
+        path.join(ALFRED_PREFS_FILE)
+    } else {
+        PathBuf::from(path)
+    };
+
+    let prefs: AlfredPrefs = plist::from_file(&path)
+        .map_err(|e| format!("Failed to parse Alfred custom searches: {}", e))?;
+
+    let mut import = ImportedBangs::default();
+    for site in prefs.custom_sites.values() {
+        let name = site.name();
+        if !site.enabled {
+            import.note(&name, "disabled in Alfred, not imported");
+            continue;
+        }
+        if !site.utf8 {
+            import.note(
+                &name,
+                "non-UTF-8 query encoding isn't supported, UTF-8 is used",
+            );
+        }
+        if let Some(spaces) = site.spaces.as_deref().filter(|spaces| !spaces.is_empty()) {
+            import.note(
+                &name,
+                &format!("replacing spaces with '{}' isn't supported", spaces),
+            );
+        }
+
+        let url = site.url.replace("{query}", "{searchTerms}");
+        import.add(Launcher::Alfred.name(), &site.keyword, &name, &url, None);
+    }
+
+    Ok(import)
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RaycastExport {
+    Quicklinks(Vec<RaycastQuicklink>),
+    Wrapped { quicklinks: Vec<RaycastQuicklink> },
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RaycastQuicklink {
+    name: String,
+    link: String,
+    #[serde(default)]
+    alias: Option<String>,
+    /// App the link is opened in, when not the default browser
+    #[serde(default, alias = "application")]
+    open_with: Option<Value>,
+}
+
+/// Maps Raycast's query placeholders to `{searchTerms}`, noting what can't be kept
+fn convert_raycast_link(import: &mut ImportedBangs, name: &str, link: &str) -> Option<String> {
+    let mut argument_names = BTreeSet::new();
+    for captures in RAYCAST_ARGUMENT.captures_iter(link) {
+        let argument_name = RAYCAST_ARGUMENT_NAME
+            .captures(&captures[1])
+            .map_or(String::new(), |name| name[1].to_string());
+        argument_names.insert(argument_name);
+
+        for modifier in captures[2].split('|').map(str::trim) {
+            if !modifier.is_empty() && modifier != "percent-encode" {
+                import.note(
+                    name,
+                    &format!(
+                        "the '{}' modifier isn't supported and was dropped",
+                        modifier
+                    ),
+                );
+            }
+        }
+    }
+
+    if argument_names.len() > 1 {
+        import.note(name, "bangs take a single query, not several arguments");
+        return None;
+    }
+
+    Some(
+        RAYCAST_ARGUMENT
+            .replace_all(link, "{searchTerms}")
+            .into_owned(),
+    )
+}
+
+fn read_raycast_quicklinks(path: &Path) -> Result<ImportedBangs, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let quicklinks = match serde_json::from_str::<RaycastExport>(&content)
+        .map_err(|e| format!("Failed to parse Raycast quicklinks: {}", e))?
+    {
+        RaycastExport::Quicklinks(quicklinks) => quicklinks,
+        RaycastExport::Wrapped { quicklinks } => quicklinks,
+    };
+
+    let mut import = ImportedBangs::default();
+    for quicklink in quicklinks {
+        let Some(url) = convert_raycast_link(&mut import, &quicklink.name, &quicklink.link) else {
+            continue;
+        };
+
+        if quicklink.open_with.is_some() {
+            import.note(
+                &quicklink.name,
+                "opening in a specific app isn't supported, it opens in the browser",
+            );
+        }
+
+        let keyword = quicklink
+            .alias
+            .clone()
+            // Raycast quicklinks have no keyword, so one is made from the name
+            .unwrap_or_else(|| keyword_from_name(&quicklink.name));
+        import.add(
+            Launcher::Raycast.name(),
+            &keyword,
+            &quicklink.name,
+            &url,
+            None,
+        );
+    }
+
+    Ok(import)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct FlowLauncherSettings {
+    #[serde(default)]
+    search_sources: Vec<FlowLauncherSource>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct FlowLauncherSource {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    action_keyword: String,
+    #[serde(default)]
+    url: String,
+    #[serde(default = "enabled")]
+    enabled: bool,
+}
+
+/// Flow Launcher's keyword for searches that run without typing a keyword first
+const FLOW_LAUNCHER_GLOBAL_KEYWORD: &str = "*";
+
+fn read_flow_launcher_searches(path: &Path) -> Result<ImportedBangs, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    // Flow Launcher writes its settings with a byte order mark
+    let settings =
+        serde_json::from_str::<FlowLauncherSettings>(content.trim_start_matches('\u{feff}'))
+            .map_err(|e| format!("Failed to parse Flow Launcher web searches: {}", e))?;
+
+    let mut import = ImportedBangs::default();
+    for source in settings.search_sources {
+        if !source.enabled {
+            import.note(&source.title, "disabled in Flow Launcher, not imported");
+            continue;
+        }
+        if source.action_keyword.trim() == FLOW_LAUNCHER_GLOBAL_KEYWORD {
+            import.note(
+                &source.title,
+                "searching without a keyword isn't supported, bangs need a trigger",
+            );
+            continue;
+        }
+
+        let url = source.url.replace("{q}", "{searchTerms}");
+        import.add(
+            Launcher::Flow.name(),
+            &source.action_keyword,
+            &source.title,
+            &url,
+            None,
+        );
+    }
+
+    Ok(import)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, name: &str, content: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    /// Trigger, name and search URL of each bang
+    fn converted(import: &ImportedBangs) -> Vec<(&str, &str, &str)> {
+        import
+            .bangs
+            .iter()
+            .map(|bang| {
+                (
+                    bang.id.as_str(),
+                    bang.name.as_str(),
+                    bang.search_url.as_str(),
+                )
+            })
+            .collect()
+    }
+
+    const ALFRED_PREFS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>customSites</key>
+    <dict>
+        <key>1A2B</key>
+        <dict>
+            <key>enabled</key><true/>
+            <key>keyword</key><string>gh</string>
+            <key>text</key><string>Search GitHub for '{query}'</string>
+            <key>url</key><string>https://github.com/search?q={query}</string>
+            <key>utf8</key><true/>
+        </dict>
+        <key>3C4D</key>
+        <dict>
+            <key>enabled</key><true/>
+            <key>keyword</key><string>wiki</string>
+            <key>text</key><string>Wikipedia {query}</string>
+            <key>url</key><string>https://en.wikipedia.org/wiki/{query}</string>
+            <key>utf8</key><false/>
+            <key>spaces</key><string>_</string>
+        </dict>
+        <key>5E6F</key>
+        <dict>
+            <key>enabled</key><false/>
+            <key>keyword</key><string>old</string>
+            <key>text</key><string>Old search</string>
+            <key>url</key><string>https://old.example/?q={query}</string>
+        </dict>
+    </dict>
+</dict>
+</plist>
+"#;
+
+    #[test]
+    fn converts_alfred_custom_searches() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write(dir.path(), "prefs.plist", ALFRED_PREFS);
+
+        let import = read_launcher_searches(&path, None).unwrap();
+
+        assert_eq!(
+            converted(&import),
+            [
+                ("gh", "Search GitHub", "https://github.com/search?q={{{s}}}"),
+                ("wiki", "Wikipedia", "https://en.wikipedia.org/wiki/{{{s}}}"),
+            ]
+        );
+        assert_eq!(import.bangs[0].category, "Imported from Alfred");
+        assert_eq!(import.bangs[0].home_url, "https://github.com");
+        assert_eq!(
+            import.unsupported,
+            [
+                "Wikipedia: non-UTF-8 query encoding isn't supported, UTF-8 is used",
+                "Wikipedia: replacing spaces with '_' isn't supported",
+                "Old search: disabled in Alfred, not imported",
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_the_prefs_in_an_alfred_preferences_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let prefs_dir = dir.path().join("Alfred.alfredpreferences");
+        let prefs_path = prefs_dir.join(ALFRED_PREFS_FILE);
+        fs::create_dir_all(prefs_path.parent().unwrap()).unwrap();
+        fs::write(&prefs_path, ALFRED_PREFS).unwrap();
+
+        let import = read_launcher_searches(&prefs_dir, None).unwrap();
+
+        assert_eq!(import.bangs.len(), 2);
+    }
+
+    #[test]
+    fn converts_raycast_quicklinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write(
+            dir.path(),
+            "quicklinks.json",
+            r#"[
+                {"name": "Google Maps", "link": "https://www.google.com/maps/search/{Query}"},
+                {"name": "Crates", "link": "https://crates.io/search?q={argument name=\"crate\" | percent-encode}", "alias": "cr"},
+                {"name": "Shout", "link": "https://example.com/?q={argument | uppercase}"},
+                {"name": "Route", "link": "https://maps.example/?from={argument name=\"from\"}&to={argument name=\"to\"}"},
+                {"name": "Docs", "link": "https://docs.example/?q={query}", "openWith": "com.apple.Safari"},
+                {"name": "Home", "link": "https://example.com"}
+            ]"#,
+        );
+
+        let import = read_launcher_searches(&path, None).unwrap();
+
+        assert_eq!(
+            converted(&import),
+            [
+                (
+                    "googlemaps",
+                    "Google Maps",
+                    "https://www.google.com/maps/search/{{{s}}}"
+                ),
+                ("cr", "Crates", "https://crates.io/search?q={{{s}}}"),
+                ("shout", "Shout", "https://example.com/?q={{{s}}}"),
+                ("docs", "Docs", "https://docs.example/?q={{{s}}}"),
+            ]
+        );
+        assert_eq!(
+            import.unsupported,
+            [
+                "Shout: the 'uppercase' modifier isn't supported and was dropped",
+                "Route: bangs take a single query, not several arguments",
+                "Docs: opening in a specific app isn't supported, it opens in the browser",
+                "Home (home): URL has no search terms placeholder",
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_wrapped_raycast_exports() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write(
+            dir.path(),
+            "export.json",
+            r#"{"quicklinks": [{"name": "Maps", "link": "https://maps.example/?q={Query}"}]}"#,
+        );
+
+        let import = read_launcher_searches(&path, Some(Launcher::Raycast)).unwrap();
+
+        assert_eq!(
+            converted(&import),
+            [("maps", "Maps", "https://maps.example/?q={{{s}}}")]
+        );
+    }
+
+    #[test]
+    fn converts_flow_launcher_web_searches() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write(
+            dir.path(),
+            "Settings.json",
+            "\u{feff}{\"SearchSources\": [
+                {\"Title\": \"Google\", \"ActionKeyword\": \"g\", \"Url\": \"https://www.google.com/search?q={q}\", \"Enabled\": true},
+                {\"Title\": \"Everywhere\", \"ActionKeyword\": \"*\", \"Url\": \"https://duckduckgo.com/?q={q}\", \"Enabled\": true},
+                {\"Title\": \"Bing\", \"ActionKeyword\": \"b\", \"Url\": \"https://www.bing.com/search?q={q}\", \"Enabled\": false}
+            ]}",
+        );
+
+        assert_eq!(Launcher::detect(&path), Ok(Launcher::Flow));
+        let import = read_launcher_searches(&path, None).unwrap();
+
+        assert_eq!(
+            converted(&import),
+            [("g", "Google", "https://www.google.com/search?q={{{s}}}")]
+        );
+        assert_eq!(import.bangs[0].category, "Imported from Flow Launcher");
+        assert_eq!(
+            import.unsupported,
+            [
+                "Everywhere: searching without a keyword isn't supported, bangs need a trigger",
+                "Bing: disabled in Flow Launcher, not imported",
+            ]
+        );
+    }
+
+    #[test]
+    fn detects_the_launcher_from_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let raycast = write(dir.path(), "quicklinks.json", "[]");
+
+        assert_eq!(Launcher::detect(&raycast), Ok(Launcher::Raycast));
+        assert_eq!(
+            Launcher::detect(Path::new("prefs.plist")),
+            Ok(Launcher::Alfred)
+        );
+        assert!(Launcher::detect(Path::new("bangs.csv")).is_err());
+    }
+}
@@ -1,9 +1,25 @@
 mod api;
+mod browsers;
+mod launchers;
 mod models;
+mod opensearch;
 mod parser;
+mod policies;
+mod revisions;
 mod storage;
+mod transfer;
 
 pub use api::{
-    add_custom_bang, delete_custom_bang, get_all_bangs, get_bang_url, load_all_bangs, refresh_bangs,
+    add_custom_bang, bang_from_opensearch, complete_bang, delete_custom_bang, diff_bang_revisions,
+    export_bangs, export_browser_policies, find_browser_profiles, get_all_bangs, get_keyword_url,
+    get_query_bang_url, import_bangs, import_browser_bangs, import_launcher_bangs,
+    list_bang_revisions, load_all_bangs, load_keywords, refresh_bangs, reload_keywords,
+    reload_user_bangs, restore_bang_revision, split_bang, split_keyword, undo_bang_change,
+    update_keyword_config,
 };
-pub use models::Bang;
+pub use browsers::BrowserProfile;
+pub use launchers::Launcher;
+pub use models::{Bang, BangQuery, KeywordConfig};
+pub use policies::{PolicyExport, PolicyFormat};
+pub use revisions::{RevisionDiff, RevisionSummary};
+pub use transfer::{BangFormat, ImportPreview, MergeStrategy};
@@ -4,7 +4,7 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::HashMap;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Bang {
     pub id: String,
     pub name: String,
@@ -12,6 +12,51 @@ pub struct Bang {
     pub home_url: String,
     pub category: String,
     pub is_custom: bool,
+    /// OpenSearch suggestions URL with `{searchTerms}`, used for suggestions while the bang is typed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggest_url: Option<String>,
+    /// Favicon of the site, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon_url: Option<String>,
+}
+
+/// Where the bang of a query was typed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BangPosition {
+    /// `!w rust`
+    BeforeTerm,
+    /// `rust !w`
+    AfterTerm,
+    /// `w rust`, with keyword mode on
+    Keyword,
+}
+
+/// A query split into the bang it uses and the remaining search term
+#[derive(Debug, Clone)]
+pub struct BangQuery {
+    pub bang: String,
+    pub term: String,
+    pub position: BangPosition,
+}
+
+impl BangQuery {
+    /// Rebuilds the query with a different search term, keeping the bang where it was typed
+    pub fn with_term(&self, term: &str) -> String {
+        match self.position {
+            BangPosition::BeforeTerm => format!("!{} {}", self.bang, term),
+            BangPosition::AfterTerm => format!("{} !{}", term, self.bang),
+            BangPosition::Keyword => format!("{} {}", self.bang, term),
+        }
+    }
+}
+
+/// Bangs that can be triggered by typing their trigger as the first word, without a `!`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KeywordConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub keywords: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -74,6 +119,8 @@ impl DuckDuckGoBang {
                 self.subcategory.as_ref().unwrap()
             ),
             is_custom: false,
+            suggest_url: None,
+            icon_url: None,
         };
 
         Some((trigger, bang))
@@ -0,0 +1,367 @@
+use crate::bangs::models::Bang;
+use crate::bangs::transfer::{convert_template, home_url_of, keyword_from_name};
+use crate::http;
+use lazy_static::lazy_static;
+use regex::Regex;
+use roxmltree::{Document, Node};
+use std::fs;
+use url::Url;
+
+const OPENSEARCH_NAMESPACE: &str = "http://a9.com/-/spec/opensearch/1.1/";
+
+const SEARCH_TYPE: &str = "text/html";
+const SUGGESTIONS_TYPE: &str = "application/x-suggestions+json";
+
+lazy_static! {
+    /// `<link rel="search" ...>` in a page, pointing to its OpenSearch description
+    static ref SEARCH_LINK: Regex = Regex::new(r#"(?is)<link\b[^>]*\brel\s*=\s*["']?search\b[^>]*>"#).unwrap();
+    static ref HREF: Regex = Regex::new(r#"(?is)\bhref\s*=\s*["']([^"']+)["']"#).unwrap();
+}
+
+/// Makes a custom bang from an OpenSearch description, read from a URL or a file.
+/// A web page's URL works too, its `<link rel="search">` is followed.
+pub async fn fetch_opensearch_bang(url_or_file: &str) -> Result<Bang, String> {
+    fetch_opensearch_bang_with(&http::client(), url_or_file).await
+}
+
+async fn fetch_opensearch_bang_with(
+    client: &reqwest::Client,
+    url_or_file: &str,
+) -> Result<Bang, String> {
+    let url_or_file = url_or_file.trim();
+
+    let url = Url::parse(url_or_file)
+        .ok()
+        .filter(|url| matches!(url.scheme(), "http" | "https"));
+    let Some(url) = url else {
+        let xml = fs::read_to_string(url_or_file)
+            .map_err(|e| format!("Failed to read {}: {}", url_or_file, e))?;
+        return parse_opensearch(&xml, None);
+    };
+
+    let content = fetch_text(client, &url).await?;
+    if is_opensearch_description(&content) {
+        return parse_opensearch(&content, Some(&url));
+    }
+
+    let description_url = find_description_link(&content, &url)
+        .ok_or_else(|| format!("{} has no OpenSearch description", url))?;
+    let xml = fetch_text(client, &description_url).await?;
+    parse_opensearch(&xml, Some(&description_url))
+}
+
+async fn fetch_text(client: &reqwest::Client, url: &Url) -> Result<String, String> {
+    let response = client
+        .get(url.clone())
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("Failed to fetch {}: HTTP {}", url, status));
+    }
+
+    response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", url, e))
+}
+
+fn is_opensearch_description(content: &str) -> bool {
+    Document::parse(content).is_ok_and(|document| {
+        document
+            .root_element()
+            .has_tag_name("OpenSearchDescription")
+    })
+}
+
+/// The OpenSearch description linked from a web page
+fn find_description_link(html: &str, page_url: &Url) -> Option<Url> {
+    SEARCH_LINK
+        .find_iter(html)
+        .map(|link| link.as_str())
+        .filter(|link| link.contains("opensearchdescription+xml"))
+        .find_map(|link| HREF.captures(link))
+        .and_then(|href| page_url.join(&href[1]).ok())
+}
+
+/// Child elements of the description with the given name. Descriptions without the
+/// namespace are common enough that the name alone is matched.
+fn children<'a>(root: Node<'a, 'a>, name: &'a str) -> impl Iterator<Item = Node<'a, 'a>> + 'a {
+    root.children().filter(move |node| {
+        node.is_element()
+            && node.tag_name().name() == name
+            && node
+                .tag_name()
+                .namespace()
+                .is_none_or(|namespace| namespace == OPENSEARCH_NAMESPACE)
+    })
+}
+
+fn text_of(root: Node, name: &str) -> Option<String> {
+    children(root, name)
+        .find_map(|node| node.text())
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
+/// The template of the `<Url>` with the given type, with its `<Param>`s added to the query
+/// string and resolved against the description's URL
+fn url_template(root: Node, kind: &str, base_url: Option<&Url>) -> Result<Option<String>, String> {
+    let Some(node) = children(root, "Url").find(|node| node.attribute("type") == Some(kind)) else {
+        return Ok(None);
+    };
+
+    if let Some(method) = node
+        .attribute("method")
+        .filter(|method| !method.eq_ignore_ascii_case("get"))
+    {
+        return Err(format!(
+            "The {} URL uses {} requests, bangs can only open GET URLs",
+            kind, method
+        ));
+    }
+
+    let mut template = node
+        .attribute("template")
+        .map(str::trim)
+        .filter(|template| !template.is_empty())
+        .ok_or_else(|| format!("The {} URL has no template", kind))?
+        .to_string();
+
+    let params: Vec<String> = children(node, "Param")
+        .filter_map(|param| Some((param.attribute("name")?, param.attribute("value")?)))
+        .map(|(name, value)| {
+            // Keep placeholders readable for the template conversion
+            let value = value
+                .split("{searchTerms}")
+                .map(|part| urlencoding::encode(part).into_owned())
+                .collect::<Vec<_>>()
+                .join("{searchTerms}");
+            format!("{}={}", urlencoding::encode(name), value)
+        })
+        .collect();
+
+    if !params.is_empty() {
+        let separator = if template.contains('?') { '&' } else { '?' };
+        template = format!("{}{}{}", template, separator, params.join("&"));
+    }
+
+    // Braces are kept as they are, so the placeholders survive resolving relative templates
+    if let Some(base_url) = base_url.filter(|_| Url::parse(&template).is_err()) {
+        let resolved = base_url
+            .join(&template)
+            .map_err(|e| format!("Invalid {} template: {}", kind, e))?;
+        template = resolved.as_str().replace("%7B", "{").replace("%7D", "}");
+    }
+
+    if Url::parse(&template).is_err() {
+        return Err(format!("The {} URL isn't a full URL: {}", kind, template));
+    }
+
+    Ok(Some(template))
+}
+
+/// Picks the favicon: a 16px or `image/x-icon` image when there's one, otherwise the first
+fn icon_url(root: Node, base_url: Option<&Url>) -> Option<String> {
+    let images: Vec<Node> = children(root, "Image").collect();
+    let image = images
+        .iter()
+        .find(|image| {
+            image.attribute("width") == Some("16")
+                || image.attribute("type") == Some("image/x-icon")
+        })
+        .or(images.first())?;
+
+    let src = image.text()?.trim();
+    match base_url {
+        Some(base_url) => base_url.join(src).ok().map(String::from),
+        None => Url::parse(src).ok().map(String::from),
+    }
+}
+
+/// Parses an OpenSearch description into a custom bang. Its trigger is made from the
+/// short name and can be changed before the bang is saved. `base_url` is where the
+/// description came from, for resolving relative URLs.
+pub fn parse_opensearch(xml: &str, base_url: Option<&Url>) -> Result<Bang, String> {
+    let document =
+        Document::parse(xml).map_err(|e| format!("Invalid OpenSearch description: {}", e))?;
+    let root = document.root_element();
+    if !root.has_tag_name("OpenSearchDescription") {
+        return Err("Not an OpenSearch description".to_string());
+    }
+
+    let name = text_of(root, "ShortName")
+        .ok_or_else(|| "The OpenSearch description has no short name".to_string())?;
+
+    let template = url_template(root, SEARCH_TYPE, base_url)?
+        .ok_or_else(|| format!("{} has no search URL", name))?;
+    let search_url =
+        convert_template(&template).map_err(|e| format!("Unsupported search URL: {}", e))?;
+
+    // Suggestions are optional, so a template that can't be used is just left out
+    let suggest_url = url_template(root, SUGGESTIONS_TYPE, base_url)
+        .ok()
+        .flatten()
+        .filter(|template| convert_template(template).is_ok());
+
+    Ok(Bang {
+        id: keyword_from_name(&name),
+        name,
+        search_url,
+        home_url: home_url_of(&template),
+        category: "Imported from OpenSearch".to_string(),
+        is_custom: true,
+        suggest_url,
+        icon_url: icon_url(root, base_url),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{self, Route};
+
+    const NAMESPACED: &str = include_str!("../../tests/fixtures/opensearch/namespaced.xml");
+    const UNNAMESPACED: &str = include_str!("../../tests/fixtures/opensearch/unnamespaced.xml");
+    const RELATIVE: &str = include_str!("../../tests/fixtures/opensearch/relative.xml");
+    const PARAMS: &str = include_str!("../../tests/fixtures/opensearch/params.xml");
+    const POST_ONLY: &str = include_str!("../../tests/fixtures/opensearch/post_only.xml");
+    const NO_SHORT_NAME: &str = include_str!("../../tests/fixtures/opensearch/no_short_name.xml");
+    const PAGE: &str = include_str!("../../tests/fixtures/opensearch/page.html");
+
+    #[test]
+    fn parses_namespaced_description() {
+        let bang = parse_opensearch(NAMESPACED, None).unwrap();
+
+        assert_eq!(bang.id, "rustdocs");
+        assert_eq!(bang.name, "Rust Docs");
+        // The optional page parameter is dropped
+        assert_eq!(
+            bang.search_url,
+            "https://doc.rust-lang.org/std/?search={{{s}}}&page="
+        );
+        assert_eq!(bang.home_url, "https://doc.rust-lang.org");
+        assert_eq!(
+            bang.suggest_url.as_deref(),
+            Some("https://doc.rust-lang.org/suggest?q={searchTerms}")
+        );
+        assert_eq!(
+            bang.icon_url.as_deref(),
+            Some("https://doc.rust-lang.org/favicon.ico")
+        );
+        assert!(bang.is_custom);
+    }
+
+    #[test]
+    fn parses_description_without_namespace() {
+        let bang = parse_opensearch(UNNAMESPACED, None).unwrap();
+
+        assert_eq!(bang.id, "crates");
+        assert_eq!(bang.search_url, "https://crates.io/search?q={{{s}}}");
+        assert_eq!(bang.suggest_url, None);
+        assert_eq!(bang.icon_url, None);
+    }
+
+    #[test]
+    fn resolves_relative_templates_against_the_description_url() {
+        let base_url = Url::parse("https://wiki.example.com/w/opensearch.xml").unwrap();
+        let bang = parse_opensearch(RELATIVE, Some(&base_url)).unwrap();
+
+        assert_eq!(bang.search_url, "https://wiki.example.com/search?q={{{s}}}");
+        assert_eq!(bang.home_url, "https://wiki.example.com");
+        assert_eq!(
+            bang.suggest_url.as_deref(),
+            Some("https://wiki.example.com/suggest?q={searchTerms}")
+        );
+        assert_eq!(
+            bang.icon_url.as_deref(),
+            Some("https://wiki.example.com/favicon.ico")
+        );
+    }
+
+    #[test]
+    fn rejects_relative_templates_without_a_base_url() {
+        let error = parse_opensearch(RELATIVE, None).unwrap_err();
+        assert!(error.contains("isn't a full URL"), "{}", error);
+    }
+
+    #[test]
+    fn adds_params_to_the_query_string() {
+        let bang = parse_opensearch(PARAMS, None).unwrap();
+
+        assert_eq!(
+            bang.search_url,
+            "https://forum.example.com/search?q={{{s}}}&source=open%20search"
+        );
+    }
+
+    #[test]
+    fn rejects_post_only_descriptions() {
+        let error = parse_opensearch(POST_ONLY, None).unwrap_err();
+        assert!(error.contains("post requests"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_descriptions_without_short_name() {
+        let error = parse_opensearch(NO_SHORT_NAME, None).unwrap_err();
+        assert!(error.contains("no short name"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn follows_search_link_of_a_web_page() {
+        let base_url = test_server::serve(vec![
+            Route::new("/", "text/html", PAGE),
+            Route::new(
+                "/opensearch.xml",
+                "application/opensearchdescription+xml",
+                RELATIVE,
+            ),
+        ]);
+
+        let bang = fetch_opensearch_bang_with(&reqwest::Client::new(), &format!("{}/", base_url))
+            .await
+            .unwrap();
+
+        assert_eq!(bang.name, "Wiki");
+        assert_eq!(
+            bang.search_url,
+            format!("{}/search?q={{{{{{s}}}}}}", base_url)
+        );
+        assert_eq!(
+            bang.suggest_url,
+            Some(format!("{}/suggest?q={{searchTerms}}", base_url))
+        );
+    }
+
+    #[tokio::test]
+    async fn fetches_description_url_directly() {
+        let base_url = test_server::serve(vec![Route::new(
+            "/opensearch.xml",
+            "application/opensearchdescription+xml",
+            UNNAMESPACED,
+        )]);
+
+        let bang = fetch_opensearch_bang_with(
+            &reqwest::Client::new(),
+            &format!("{}/opensearch.xml", base_url),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(bang.search_url, "https://crates.io/search?q={{{s}}}");
+    }
+
+    #[tokio::test]
+    async fn reports_pages_without_search_link() {
+        let base_url = test_server::serve(vec![Route::new("/", "text/html", "<html></html>")]);
+
+        let error = fetch_opensearch_bang_with(&reqwest::Client::new(), &format!("{}/", base_url))
+            .await
+            .unwrap_err();
+
+        assert!(error.contains("has no OpenSearch description"), "{}", error);
+    }
+}
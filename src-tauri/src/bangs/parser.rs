@@ -1,4 +1,5 @@
 use crate::bangs::models::{Bang, DuckDuckGoBang};
+use crate::http;
 use crate::logger;
 use std::collections::{HashMap, HashSet};
 
@@ -130,6 +131,8 @@ pub(crate) fn parse_duckduckgo_bangs(js_content: &str) -> Result<HashMap<String,
                                 home_url: format!("https://{}", domain),
                                 category: format!("{} - {}", category, subcategory),
                                 is_custom: false,
+                                suggest_url: None,
+                                icon_url: None,
                             };
 
                             bangs.insert(trigger.to_string(), bang);
@@ -167,14 +170,8 @@ pub(crate) fn parse_duckduckgo_bangs(js_content: &str) -> Result<HashMap<String,
 pub(crate) async fn fetch_duckduckgo_bangs() -> Result<HashMap<String, Bang>, String> {
     logger::info("Fetching DuckDuckGo bangs...");
 
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
-
-    let response = client
+    let response = http::client()
         .get("https://duckduckgo.com/bang.js")
-        .header("User-Agent", "Zephyr/1.0")
         .send()
         .await
         .map_err(|e| format!("Failed to fetch bangs: {}", e))?;
@@ -0,0 +1,289 @@
+use crate::bangs::models::Bang;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// OpenSearch limits short names to 16 characters
+const SHORT_NAME_LENGTH: usize = 16;
+
+/// Browser configurations bangs can be exported to, for people without Zephyr
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyFormat {
+    /// A `policies.json` adding the bangs as Firefox search engines
+    Firefox,
+    /// The `ManagedSearchEngines` policy for Chrome, Edge and Brave
+    Chromium,
+    /// An OpenSearch description per bang, written to a directory
+    OpenSearch,
+}
+
+/// Bangs exported as browser search engines
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PolicyExport {
+    /// Triggers of the bangs that were exported
+    pub exported: Vec<String>,
+    /// Bangs whose search URL browsers can't express, and why
+    pub unsupported: Vec<String>,
+}
+
+/// A bang with its URLs in OpenSearch syntax
+pub struct SearchEngine<'a> {
+    pub bang: &'a Bang,
+    pub search_url: String,
+    pub suggest_url: Option<String>,
+}
+
+/// Converts a bang template to an OpenSearch one. Browsers always encode the search terms,
+/// so a raw `{{q}}` query has no equivalent.
+pub fn to_opensearch_template(template: &str) -> Result<String, String> {
+    if template.contains("{{q}}") {
+        return Err("the query is inserted without encoding, which browsers can't do".to_string());
+    }
+
+    let converted = template
+        .replace("{{{s}}}", "{searchTerms}")
+        .replace("{{{qe}}}", "{searchTerms}")
+        .replace("{{qe}}", "{searchTerms}");
+
+    check_opensearch_template(&converted, "search URL")?;
+    Ok(converted)
+}
+
+/// Checks an OpenSearch template has the query placeholder and no other parameters
+fn check_opensearch_template(template: &str, kind: &str) -> Result<(), String> {
+    if !template.contains("{searchTerms}") {
+        return Err(format!("the {} has no query placeholder", kind));
+    }
+
+    // Browsers would read any other braces as OpenSearch parameters
+    if template.replace("{searchTerms}", "").contains(['{', '}']) {
+        return Err(format!(
+            "the {} has braces browsers would treat as parameters",
+            kind
+        ));
+    }
+
+    Ok(())
+}
+
+/// Converts the bangs that browsers can use, noting why the others can't be.
+/// A suggestions URL browsers can't use is left out, the bang is exported without it.
+pub fn to_search_engines<'a>(bangs: &[&'a Bang]) -> (Vec<SearchEngine<'a>>, Vec<String>) {
+    let mut engines = Vec::new();
+    let mut unsupported = Vec::new();
+
+    for bang in bangs {
+        let search_url = match to_opensearch_template(&bang.search_url) {
+            Ok(search_url) => search_url,
+            Err(e) => {
+                unsupported.push(format!("{} ({}): {}", bang.name, bang.id, e));
+                continue;
+            }
+        };
+
+        // Suggestions already use `{searchTerms}`
+        let mut suggest_url = bang.suggest_url.clone();
+        if let Some(Err(e)) = suggest_url
+            .as_deref()
+            .map(|suggest_url| check_opensearch_template(suggest_url, "suggestions URL"))
+        {
+            unsupported.push(format!(
+                "{} ({}): exported without suggestions, {}",
+                bang.name, bang.id, e
+            ));
+            suggest_url = None;
+        }
+
+        engines.push(SearchEngine {
+            bang,
+            search_url,
+            suggest_url,
+        });
+    }
+
+    (engines, unsupported)
+}
+
+/// `policies.json` adding the engines to Firefox, with the triggers as keywords
+pub fn format_firefox_policies(engines: &[SearchEngine]) -> Result<String, String> {
+    let add: Vec<_> = engines
+        .iter()
+        .map(|engine| {
+            let mut entry = json!({
+                "Name": engine.bang.name,
+                "URLTemplate": engine.search_url,
+                "Method": "GET",
+                "Alias": engine.bang.id,
+                "Description": description(engine.bang),
+            });
+            if let Some(suggest_url) = &engine.suggest_url {
+                entry["SuggestURLTemplate"] = json!(suggest_url);
+            }
+            if let Some(icon_url) = &engine.bang.icon_url {
+                entry["IconURL"] = json!(icon_url);
+            }
+            entry
+        })
+        .collect();
+
+    let policies = json!({ "policies": { "SearchEngines": { "Add": add } } });
+    serde_json::to_string_pretty(&policies).map_err(|e| e.to_string())
+}
+
+/// The `ManagedSearchEngines` policy for Chromium browsers, with the triggers as keywords
+pub fn format_chromium_policy(engines: &[SearchEngine]) -> Result<String, String> {
+    let managed: Vec<_> = engines
+        .iter()
+        .map(|engine| {
+            let mut entry = json!({
+                "name": engine.bang.name,
+                "keyword": engine.bang.id,
+                "url": engine.search_url,
+            });
+            if let Some(suggest_url) = &engine.suggest_url {
+                entry["suggest_url"] = json!(suggest_url);
+            }
+            if let Some(icon_url) = &engine.bang.icon_url {
+                entry["favicon_url"] = json!(icon_url);
+            }
+            entry
+        })
+        .collect();
+
+    let policy = json!({ "ManagedSearchEngines": managed });
+    serde_json::to_string_pretty(&policy).map_err(|e| e.to_string())
+}
+
+fn description(bang: &Bang) -> String {
+    format!("{} (!{})", bang.name, bang.id)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// An OpenSearch description of a single engine
+pub fn format_opensearch(engine: &SearchEngine) -> String {
+    let bang = engine.bang;
+    let short_name: String = bang.name.chars().take(SHORT_NAME_LENGTH).collect();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<OpenSearchDescription xmlns=\"http://a9.com/-/spec/opensearch/1.1/\">\n");
+    xml.push_str(&format!(
+        "  <ShortName>{}</ShortName>\n",
+        escape_xml(&short_name)
+    ));
+    xml.push_str(&format!(
+        "  <Description>{}</Description>\n",
+        escape_xml(&description(bang))
+    ));
+    xml.push_str("  <InputEncoding>UTF-8</InputEncoding>\n");
+    if let Some(icon_url) = &bang.icon_url {
+        xml.push_str(&format!(
+            "  <Image width=\"16\" height=\"16\">{}</Image>\n",
+            escape_xml(icon_url)
+        ));
+    }
+    xml.push_str(&format!(
+        "  <Url type=\"text/html\" method=\"get\" template=\"{}\"/>\n",
+        escape_xml(&engine.search_url)
+    ));
+    if let Some(suggest_url) = &engine.suggest_url {
+        xml.push_str(&format!(
+            "  <Url type=\"application/x-suggestions+json\" template=\"{}\"/>\n",
+            escape_xml(suggest_url)
+        ));
+    }
+    xml.push_str("</OpenSearchDescription>\n");
+    xml
+}
+
+/// File name of a bang's OpenSearch description, with characters that aren't safe
+/// in file names replaced
+pub fn opensearch_file_name(bang: &Bang) -> String {
+    let name: String = bang
+        .id
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}.xml", name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bang(search_url: &str, suggest_url: Option<&str>) -> Bang {
+        Bang {
+            id: "ex".to_string(),
+            name: "Example".to_string(),
+            search_url: search_url.to_string(),
+            home_url: "https://example.com".to_string(),
+            category: "Online Services".to_string(),
+            is_custom: true,
+            suggest_url: suggest_url.map(str::to_string),
+            icon_url: None,
+        }
+    }
+
+    #[test]
+    fn drops_suggestion_urls_browsers_cant_use() {
+        let valid = bang(
+            "https://example.com/?q={{{s}}}",
+            Some("https://example.com/suggest?q={searchTerms}"),
+        );
+        let extra_param = bang(
+            "https://example.com/?q={{{s}}}",
+            Some("https://example.com/suggest?q={searchTerms}&n={count}"),
+        );
+        let no_placeholder = bang(
+            "https://example.com/?q={{{s}}}",
+            Some("https://example.com/suggest"),
+        );
+
+        let (engines, unsupported) = to_search_engines(&[&valid, &extra_param, &no_placeholder]);
+
+        assert_eq!(engines.len(), 3);
+        assert_eq!(
+            engines[0].suggest_url.as_deref(),
+            Some("https://example.com/suggest?q={searchTerms}")
+        );
+        assert_eq!(engines[1].suggest_url, None);
+        assert_eq!(engines[2].suggest_url, None);
+        assert_eq!(unsupported.len(), 2);
+        assert!(
+            unsupported[0].contains("suggestions URL has braces"),
+            "{}",
+            unsupported[0]
+        );
+        assert!(
+            unsupported[1].contains("no query placeholder"),
+            "{}",
+            unsupported[1]
+        );
+    }
+
+    #[test]
+    fn skips_bangs_with_raw_queries() {
+        let raw = bang("https://example.com/{{q}}", None);
+
+        let (engines, unsupported) = to_search_engines(&[&raw]);
+
+        assert!(engines.is_empty());
+        assert!(
+            unsupported[0].contains("without encoding"),
+            "{}",
+            unsupported[0]
+        );
+    }
+}
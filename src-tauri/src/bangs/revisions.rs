@@ -0,0 +1,264 @@
+use crate::bangs::models::Bang;
+use crate::logger;
+use crate::persist::{self, Schema};
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Revisions kept, the oldest are dropped first
+const MAX_REVISIONS: usize = 100;
+
+/// How long the last destructive change can be undone
+const UNDO_WINDOW: Duration = Duration::from_secs(30);
+
+const REVISIONS_SCHEMA: Schema = Schema::new(&[persist::unchanged]);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RevisionAction {
+    /// The bangs as they were before the first recorded change
+    Initial,
+    Add,
+    Update,
+    Delete,
+    Import,
+    Restore,
+    Undo,
+    /// The user bangs file was changed outside the app
+    EditedOnDisk,
+}
+
+impl RevisionAction {
+    /// Changes that overwrite or remove bangs, which can be undone for a short while
+    fn is_destructive(self) -> bool {
+        matches!(
+            self,
+            RevisionAction::Update
+                | RevisionAction::Delete
+                | RevisionAction::Import
+                | RevisionAction::Restore
+        )
+    }
+}
+
+/// The user bangs after a change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Revision {
+    pub id: u64,
+    pub timestamp: DateTime<Utc>,
+    pub action: RevisionAction,
+    /// Bangs the change was made to
+    pub bang_ids: Vec<String>,
+    pub bangs: HashMap<String, Bang>,
+}
+
+/// A revision without its bangs, for listing
+#[derive(Debug, Clone, Serialize)]
+pub struct RevisionSummary {
+    pub id: u64,
+    pub timestamp: DateTime<Utc>,
+    pub action: RevisionAction,
+    pub bang_ids: Vec<String>,
+    pub bang_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BangChange {
+    pub before: Bang,
+    pub after: Bang,
+}
+
+/// Differences between two revisions, sorted by trigger
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RevisionDiff {
+    pub added: Vec<Bang>,
+    pub removed: Vec<Bang>,
+    pub changed: Vec<BangChange>,
+}
+
+struct PendingUndo {
+    revision_id: u64,
+    expires_at: Instant,
+}
+
+lazy_static! {
+    static ref PENDING_UNDO: Mutex<Option<PendingUndo>> = Mutex::new(None);
+}
+
+fn get_revisions_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("zephyr")
+        .join("user_bangs_revisions.json")
+}
+
+fn load_revisions() -> Vec<Revision> {
+    match persist::read(&get_revisions_path(), &REVISIONS_SCHEMA) {
+        Ok(revisions) => revisions.unwrap_or_default(),
+        Err(e) => {
+            logger::error(&format!("Failed to load bang revisions: {}", e));
+            Vec::new()
+        }
+    }
+}
+
+fn save_revisions(revisions: &[Revision]) -> Result<(), String> {
+    persist::write(&get_revisions_path(), &REVISIONS_SCHEMA, &revisions)
+}
+
+/// Records the user bangs after a change. Changes that overwrite or remove bangs can
+/// be undone with `take_undo` for a short while.
+pub fn record(
+    before: &HashMap<String, Bang>,
+    after: &HashMap<String, Bang>,
+    action: RevisionAction,
+    bang_ids: Vec<String>,
+) {
+    let mut revisions = load_revisions();
+
+    // Without the state before the first change, it couldn't be restored or undone
+    if revisions.is_empty() {
+        revisions.push(Revision {
+            id: 1,
+            timestamp: Utc::now(),
+            action: RevisionAction::Initial,
+            bang_ids: Vec::new(),
+            bangs: before.clone(),
+        });
+    }
+
+    let id = revisions.last().map_or(1, |revision| revision.id + 1);
+    revisions.push(Revision {
+        id,
+        timestamp: Utc::now(),
+        action,
+        bang_ids,
+        bangs: after.clone(),
+    });
+
+    if revisions.len() > MAX_REVISIONS {
+        revisions.drain(..revisions.len() - MAX_REVISIONS);
+    }
+
+    if let Err(e) = save_revisions(&revisions) {
+        logger::error(&format!("Failed to save bang revision: {}", e));
+        return;
+    }
+
+    *PENDING_UNDO.lock().unwrap() = action.is_destructive().then(|| PendingUndo {
+        revision_id: id,
+        expires_at: Instant::now() + UNDO_WINDOW,
+    });
+}
+
+/// Records bangs changed outside the app, unless they match the latest revision.
+/// The app records its own changes as it makes them, so those always match.
+pub fn record_if_changed(before: &HashMap<String, Bang>, after: &HashMap<String, Bang>) {
+    let unchanged = match load_revisions().last() {
+        Some(latest) => latest.bangs == *after,
+        None => before == after,
+    };
+
+    if !unchanged {
+        let bang_ids = diff(before, after).bang_ids();
+        record(before, after, RevisionAction::EditedOnDisk, bang_ids);
+    }
+}
+
+impl RevisionDiff {
+    /// Triggers of every bang that differs
+    pub fn bang_ids(&self) -> Vec<String> {
+        let mut bang_ids: Vec<String> = self
+            .added
+            .iter()
+            .chain(&self.removed)
+            .chain(self.changed.iter().map(|change| &change.after))
+            .map(|bang| bang.id.clone())
+            .collect();
+        bang_ids.sort();
+        bang_ids
+    }
+}
+
+pub fn diff(before: &HashMap<String, Bang>, after: &HashMap<String, Bang>) -> RevisionDiff {
+    let mut diff = RevisionDiff::default();
+
+    for (id, bang) in after {
+        match before.get(id) {
+            None => diff.added.push(bang.clone()),
+            Some(previous) if previous != bang => diff.changed.push(BangChange {
+                before: previous.clone(),
+                after: bang.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+
+    diff.removed = before
+        .iter()
+        .filter(|(id, _)| !after.contains_key(*id))
+        .map(|(_, bang)| bang.clone())
+        .collect();
+
+    diff.added.sort_by(|a, b| a.id.cmp(&b.id));
+    diff.removed.sort_by(|a, b| a.id.cmp(&b.id));
+    diff.changed.sort_by(|a, b| a.after.id.cmp(&b.after.id));
+    diff
+}
+
+/// Revisions from newest to oldest
+pub fn list_revisions() -> Vec<RevisionSummary> {
+    load_revisions()
+        .into_iter()
+        .rev()
+        .map(|revision| RevisionSummary {
+            id: revision.id,
+            timestamp: revision.timestamp,
+            action: revision.action,
+            bang_ids: revision.bang_ids,
+            bang_count: revision.bangs.len(),
+        })
+        .collect()
+}
+
+pub fn get_revision(revision_id: u64) -> Result<Revision, String> {
+    load_revisions()
+        .into_iter()
+        .find(|revision| revision.id == revision_id)
+        .ok_or_else(|| format!("Revision not found: {}", revision_id))
+}
+
+/// What changed going from one revision to the other
+pub fn diff_revisions(from_id: u64, to_id: u64) -> Result<RevisionDiff, String> {
+    let from = get_revision(from_id)?;
+    let to = get_revision(to_id)?;
+    Ok(diff(&from.bangs, &to.bangs))
+}
+
+/// The bangs from before the last destructive change, if it can still be undone.
+/// Taking them ends the undo window.
+pub fn take_undo() -> Result<HashMap<String, Bang>, String> {
+    let pending = PENDING_UNDO
+        .lock()
+        .unwrap()
+        .take()
+        .filter(|pending| pending.expires_at > Instant::now())
+        .ok_or_else(|| "Nothing to undo".to_string())?;
+
+    let revisions = load_revisions();
+    let index = revisions
+        .iter()
+        .position(|revision| revision.id == pending.revision_id)
+        .ok_or_else(|| "Nothing to undo".to_string())?;
+
+    // Something else changed the bangs since, undoing would throw that away too
+    if index + 1 != revisions.len() || index == 0 {
+        return Err("Nothing to undo".to_string());
+    }
+
+    Ok(revisions[index - 1].bangs.clone())
+}
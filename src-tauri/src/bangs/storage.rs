@@ -1,11 +1,19 @@
-use crate::bangs::models::{Bang, BangCache};
+use crate::bangs::models::{Bang, BangCache, KeywordConfig};
 use crate::logger;
+use crate::persist::{self, Schema};
 use chrono::Utc;
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 use tauri::AppHandle;
 
+// Version 1 of each file added the versioned envelope, later versions add a migration each
+const CACHE_SCHEMA: Schema = Schema::new(&[persist::unchanged]).without_backups();
+
+const USER_BANGS_SCHEMA: Schema = Schema::new(&[persist::unchanged]);
+
+const KEYWORDS_SCHEMA: Schema = Schema::new(&[persist::unchanged]);
+
 pub(crate) fn get_cache_path(_app_handle: &AppHandle) -> PathBuf {
     dirs::cache_dir()
         .unwrap_or_else(|| PathBuf::from("."))
@@ -20,81 +28,86 @@ pub(crate) fn get_settings_path(_app_handle: &AppHandle) -> PathBuf {
         .join("user_bangs.json")
 }
 
-pub(crate) fn load_cache(app_handle: &AppHandle) -> Option<BangCache> {
-    let cache_path = get_cache_path(app_handle);
-
-    if !cache_path.exists() {
-        return None;
-    }
+pub(crate) fn get_keywords_path(_app_handle: &AppHandle) -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("zephyr")
+        .join("keywords.json")
+}
 
-    match fs::read_to_string(&cache_path) {
-        Ok(json) => match serde_json::from_str::<BangCache>(&json) {
-            Ok(cache) => Some(cache),
-            Err(e) => {
-                logger::error(&format!("Failed to parse bang cache: {}", e));
-                None
-            }
-        },
-        Err(e) => {
-            logger::error(&format!("Failed to read bang cache: {}", e));
-            None
-        }
-    }
+pub(crate) fn load_cache(app_handle: &AppHandle) -> Option<BangCache> {
+    persist::read::<BangCache>(&get_cache_path(app_handle), &CACHE_SCHEMA).unwrap_or_else(|e| {
+        logger::error(&format!("Failed to load bang cache: {}", e));
+        None
+    })
 }
 
 pub(crate) fn save_cache(
     app_handle: &AppHandle,
     bangs: &HashMap<String, Bang>,
 ) -> Result<(), String> {
-    let cache_path = get_cache_path(app_handle);
-
-    if let Some(parent) = cache_path.parent() {
-        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
-    }
-
     let cache = BangCache {
         bangs: bangs.clone(),
         last_updated: Utc::now(),
     };
 
-    let json = serde_json::to_string_pretty(&cache).map_err(|e| e.to_string())?;
-    fs::write(&cache_path, json).map_err(|e| e.to_string())
+    persist::write(&get_cache_path(app_handle), &CACHE_SCHEMA, &cache)
 }
 
 pub(crate) fn load_user_bangs(app_handle: &AppHandle) -> HashMap<String, Bang> {
-    let settings_path = get_settings_path(app_handle);
-
-    if !settings_path.exists() {
-        return HashMap::new();
-    }
-
-    match fs::read_to_string(&settings_path) {
-        Ok(json) => match serde_json::from_str::<HashMap<String, Bang>>(&json) {
-            Ok(bangs) => bangs,
-            Err(e) => {
-                logger::error(&format!("Failed to parse user bangs: {}", e));
-                HashMap::new()
-            }
-        },
-        Err(e) => {
-            logger::error(&format!("Failed to read user bangs: {}", e));
+    persist::read(&get_settings_path(app_handle), &USER_BANGS_SCHEMA)
+        .map(Option::unwrap_or_default)
+        .unwrap_or_else(|e| {
+            logger::error(&format!("Failed to load user bangs: {}", e));
             HashMap::new()
-        }
-    }
+        })
+}
+
+/// Reads the user bangs as they are on disk, reporting errors instead of falling back
+/// to a backup or to no bangs
+pub(crate) fn read_user_bangs(app_handle: &AppHandle) -> Result<HashMap<String, Bang>, String> {
+    persist::read_without_recovery(&get_settings_path(app_handle), &USER_BANGS_SCHEMA)
+        .map(Option::unwrap_or_default)
+        .map_err(|e| format!("Failed to load user bangs: {}", e))
 }
 
 pub(crate) fn save_user_bangs(
     app_handle: &AppHandle,
     bangs: &HashMap<String, Bang>,
 ) -> Result<(), String> {
-    let settings_path = get_settings_path(app_handle);
+    persist::write(&get_settings_path(app_handle), &USER_BANGS_SCHEMA, bangs)
+}
 
-    if let Some(parent) = settings_path.parent() {
-        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
-    }
+/// User bangs in the same format as the user bangs file
+pub(crate) fn user_bangs_to_json(bangs: &HashMap<String, Bang>) -> Result<String, String> {
+    persist::to_string(&USER_BANGS_SCHEMA, bangs)
+}
 
-    let json = serde_json::to_string_pretty(&bangs).map_err(|e| e.to_string())?;
-    fs::write(&settings_path, json).map_err(|e| e.to_string())
+/// Reads user bangs copied from a user bangs file of any version
+pub(crate) fn user_bangs_from_json(json: &str) -> Result<HashMap<String, Bang>, String> {
+    persist::from_str(json, &USER_BANGS_SCHEMA)
+}
+
+pub(crate) fn load_keyword_config(app_handle: &AppHandle) -> KeywordConfig {
+    persist::read(&get_keywords_path(app_handle), &KEYWORDS_SCHEMA)
+        .map(Option::unwrap_or_default)
+        .unwrap_or_else(|e| {
+            logger::error(&format!("Failed to load keyword config: {}", e));
+            KeywordConfig::default()
+        })
+}
+
+pub(crate) fn read_keyword_config(app_handle: &AppHandle) -> Result<KeywordConfig, String> {
+    persist::read_without_recovery(&get_keywords_path(app_handle), &KEYWORDS_SCHEMA)
+        .map(Option::unwrap_or_default)
+        .map_err(|e| format!("Failed to load keyword config: {}", e))
+}
+
+pub(crate) fn save_keyword_config(
+    app_handle: &AppHandle,
+    config: &KeywordConfig,
+) -> Result<(), String> {
+    persist::write(&get_keywords_path(app_handle), &KEYWORDS_SCHEMA, config)
 }
 
 pub(crate) fn delete_cache(app_handle: &AppHandle) -> Result<(), String> {
@@ -0,0 +1,356 @@
+use crate::bangs::models::Bang;
+use crate::bangs::parser::parse_duckduckgo_bangs;
+use crate::bangs::storage::{user_bangs_from_json, user_bangs_to_json};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BangFormat {
+    /// The format of the user bangs file
+    Json,
+    Toml,
+    /// One bang per row, with a header row naming the columns
+    Csv,
+    /// A JSON array like DuckDuckGo's bang.js
+    DuckDuckGo,
+}
+
+impl BangFormat {
+    /// Guesses the format from the file extension, telling the JSON formats apart by content
+    pub fn detect(path: &Path, content: &str) -> Result<Self, String> {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+
+        match extension.as_deref() {
+            Some("toml") => Ok(BangFormat::Toml),
+            Some("csv") => Ok(BangFormat::Csv),
+            Some("js") => Ok(BangFormat::DuckDuckGo),
+            Some("json") if content.trim_start().starts_with('[') => Ok(BangFormat::DuckDuckGo),
+            Some("json") => Ok(BangFormat::Json),
+            _ => Err(format!("Unknown bang file format: {}", path.display())),
+        }
+    }
+}
+
+/// What to do with imported bangs whose trigger is already taken by a different bang
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    /// Keep the existing bang
+    #[default]
+    Skip,
+    /// Replace the existing bang
+    Overwrite,
+    /// Import the bang under a free trigger, e.g. `gh2`
+    Rename,
+}
+
+/// Imported bangs compared with the ones in use
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportPreview {
+    pub new: Vec<Bang>,
+    pub conflicting: Vec<ImportConflict>,
+    /// Triggers of imported bangs that match the existing ones exactly
+    pub identical: Vec<String>,
+    /// Imported bangs that can't be used, e.g. because their search URL is invalid
+    pub invalid: Vec<String>,
+    /// Entries of the source, or features of them, that have no bang equivalent
+    pub unsupported: Vec<String>,
+}
+
+/// An imported bang whose trigger is taken by a different bang
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportConflict {
+    pub existing: Bang,
+    pub imported: Bang,
+    /// Whether the existing bang is one of DuckDuckGo's rather than the user's own
+    pub existing_builtin: bool,
+    /// Trigger the imported bang gets when renaming, e.g. `gh2`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub renamed_to: Option<String>,
+}
+
+/// Bangs converted from another app's search shortcuts
+#[derive(Debug, Default)]
+pub struct ImportedBangs {
+    pub bangs: Vec<Bang>,
+    /// Shortcuts or features that couldn't be converted, and why
+    pub unsupported: Vec<String>,
+}
+
+impl ImportedBangs {
+    /// Converts a shortcut with an OpenSearch (`{searchTerms}`) or `%s` URL template,
+    /// noting why when it can't be
+    pub fn add(
+        &mut self,
+        source: &str,
+        keyword: &str,
+        name: &str,
+        url: &str,
+        suggest_url: Option<&str>,
+    ) {
+        let keyword = keyword.trim().trim_start_matches('@');
+        if keyword.is_empty() || keyword.contains(char::is_whitespace) {
+            self.unsupported
+                .push(format!("{}: no keyword that can be used as a bang", name));
+            return;
+        }
+
+        let search_url = match convert_template(url) {
+            Ok(search_url) => search_url,
+            Err(e) => {
+                self.unsupported
+                    .push(format!("{} ({}): {}", name, keyword, e));
+                return;
+            }
+        };
+
+        self.bangs.push(Bang {
+            id: keyword.to_string(),
+            name: name.to_string(),
+            search_url,
+            home_url: home_url_of(url),
+            category: format!("Imported from {}", source),
+            is_custom: true,
+            // Suggestions are optional, so a template that can't be converted is just left out
+            suggest_url: suggest_url
+                .filter(|url| !url.trim().is_empty())
+                .map(|url| url.replace("%s", "{searchTerms}"))
+                .filter(|url| convert_template(url).is_ok()),
+            icon_url: None,
+        });
+    }
+
+    /// Notes a feature of an imported shortcut that's lost in the conversion
+    pub fn note(&mut self, name: &str, message: &str) {
+        self.unsupported.push(format!("{}: {}", name, message));
+    }
+}
+
+/// A bang as written to TOML and CSV files, where only the trigger, name and URL are required
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BangRecord {
+    id: String,
+    name: String,
+    search_url: String,
+    #[serde(default)]
+    home_url: String,
+    #[serde(default)]
+    category: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    suggest_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    icon_url: Option<String>,
+}
+
+impl From<&Bang> for BangRecord {
+    fn from(bang: &Bang) -> Self {
+        Self {
+            id: bang.id.clone(),
+            name: bang.name.clone(),
+            search_url: bang.search_url.clone(),
+            home_url: bang.home_url.clone(),
+            category: bang.category.clone(),
+            suggest_url: bang.suggest_url.clone(),
+            icon_url: bang.icon_url.clone(),
+        }
+    }
+}
+
+impl From<BangRecord> for Bang {
+    fn from(record: BangRecord) -> Self {
+        let home_url = if record.home_url.trim().is_empty() {
+            home_url_of(&record.search_url)
+        } else {
+            record.home_url
+        };
+
+        Bang {
+            id: record.id,
+            name: record.name,
+            search_url: record.search_url,
+            home_url,
+            category: record.category,
+            is_custom: true,
+            suggest_url: record.suggest_url.filter(|url| !url.trim().is_empty()),
+            icon_url: record.icon_url.filter(|url| !url.trim().is_empty()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct TomlBangs {
+    #[serde(rename = "bang", default)]
+    bangs: Vec<BangRecord>,
+}
+
+/// DuckDuckGo's bang.js entry, see `DuckDuckGoBang` for the field names
+#[derive(Serialize)]
+struct DuckDuckGoRecord {
+    c: String,
+    d: String,
+    r: i32,
+    s: String,
+    sc: String,
+    t: String,
+    u: String,
+}
+
+/// Origin of a search URL, used as the home page when none is given
+pub(crate) fn home_url_of(search_url: &str) -> String {
+    url::Url::parse(search_url)
+        .map(|url| url.origin().ascii_serialization())
+        .unwrap_or_default()
+}
+
+/// A trigger made from a name, for sources without keywords: `Google Maps` → `googlemaps`
+pub(crate) fn keyword_from_name(name: &str) -> String {
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Converts an OpenSearch (`{searchTerms}`) or browser keyword (`%s`) URL template to
+/// the bang placeholder. Optional OpenSearch parameters like `{startPage?}` are dropped,
+/// other parameters have no bang equivalent.
+pub fn convert_template(template: &str) -> Result<String, String> {
+    // Stands in for the search terms while the remaining placeholders are checked
+    const SEARCH_TERMS: &str = "\u{0}";
+
+    let template = template
+        .trim()
+        .replace("{searchTerms}", SEARCH_TERMS)
+        .replace("%s", SEARCH_TERMS)
+        .replace("{inputEncoding}", "UTF-8")
+        .replace("{outputEncoding}", "UTF-8");
+
+    if !template.contains(SEARCH_TERMS) {
+        return Err("URL has no search terms placeholder".to_string());
+    }
+
+    let mut converted = String::new();
+    let mut rest = template.as_str();
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| "URL has an unclosed placeholder".to_string())?;
+        let parameter = &rest[start + 1..end];
+
+        if !parameter.ends_with('?') {
+            return Err(format!("Unsupported placeholder {{{}}}", parameter));
+        }
+
+        converted.push_str(&rest[..start]);
+        rest = &rest[end + 1..];
+    }
+    converted.push_str(rest);
+
+    Ok(converted.replace(SEARCH_TERMS, "{{{s}}}"))
+}
+
+/// Whether two bangs only differ in being built-in or custom
+pub fn is_same_bang(a: &Bang, b: &Bang) -> bool {
+    a.id == b.id
+        && a.name == b.name
+        && a.search_url == b.search_url
+        && a.home_url == b.home_url
+        && a.category == b.category
+        && a.suggest_url == b.suggest_url
+        && a.icon_url == b.icon_url
+}
+
+/// Bangs sorted by trigger, so exports are stable
+fn sorted(bangs: &HashMap<String, Bang>) -> Vec<&Bang> {
+    let mut sorted: Vec<&Bang> = bangs.values().collect();
+    sorted.sort_by(|a, b| a.id.cmp(&b.id));
+    sorted
+}
+
+pub fn format_bangs(bangs: &HashMap<String, Bang>, format: BangFormat) -> Result<String, String> {
+    match format {
+        BangFormat::Json => user_bangs_to_json(bangs),
+        BangFormat::Toml => {
+            let toml_bangs = TomlBangs {
+                bangs: sorted(bangs).into_iter().map(BangRecord::from).collect(),
+            };
+            toml::to_string_pretty(&toml_bangs).map_err(|e| e.to_string())
+        }
+        BangFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            for bang in sorted(bangs) {
+                writer
+                    .serialize(BangRecord::from(bang))
+                    .map_err(|e| e.to_string())?;
+            }
+
+            let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+            String::from_utf8(bytes).map_err(|e| e.to_string())
+        }
+        BangFormat::DuckDuckGo => {
+            let records: Vec<DuckDuckGoRecord> = sorted(bangs)
+                .into_iter()
+                .map(|bang| {
+                    let (category, subcategory) = match bang.category.split_once(" - ") {
+                        Some(categories) => categories,
+                        None if bang.category.is_empty() => ("Custom", "Custom"),
+                        None => (bang.category.as_str(), "Custom"),
+                    };
+                    let domain = url::Url::parse(&bang.home_url)
+                        .ok()
+                        .and_then(|url| url.host_str().map(str::to_string))
+                        .unwrap_or_default();
+
+                    DuckDuckGoRecord {
+                        c: category.to_string(),
+                        d: domain,
+                        r: 0,
+                        s: bang.name.clone(),
+                        sc: subcategory.to_string(),
+                        t: bang.id.clone(),
+                        u: bang.search_url.clone(),
+                    }
+                })
+                .collect();
+
+            serde_json::to_string_pretty(&records).map_err(|e| e.to_string())
+        }
+    }
+}
+
+pub fn parse_bangs(content: &str, format: BangFormat) -> Result<Vec<Bang>, String> {
+    let bangs: Vec<Bang> = match format {
+        BangFormat::Json => user_bangs_from_json(content)?.into_values().collect(),
+        BangFormat::Toml => toml::from_str::<TomlBangs>(content)
+            .map_err(|e| e.to_string())?
+            .bangs
+            .into_iter()
+            .map(Bang::from)
+            .collect(),
+        BangFormat::Csv => csv::Reader::from_reader(content.as_bytes())
+            .deserialize::<BangRecord>()
+            .map(|record| record.map(Bang::from).map_err(|e| e.to_string()))
+            .collect::<Result<_, _>>()?,
+        BangFormat::DuckDuckGo => parse_duckduckgo_bangs(content)?.into_values().collect(),
+    };
+
+    Ok(bangs
+        .into_iter()
+        .map(|bang| Bang {
+            is_custom: true,
+            ..bang
+        })
+        .collect())
+}
+
+/// The first trigger not in use, counting up from 2: `gh2`, `gh3`, ...
+pub fn free_trigger(id: &str, is_taken: impl Fn(&str) -> bool) -> String {
+    (2..)
+        .map(|n| format!("{}{}", id, n))
+        .find(|candidate| !is_taken(candidate))
+        .unwrap_or_else(|| id.to_string())
+}
@@ -0,0 +1,202 @@
+use crate::bangs;
+use crate::detectors;
+use crate::history;
+use crate::http;
+use crate::locale;
+use crate::logger;
+use crate::persist;
+use crate::rules;
+use crate::search::{BangState, RuleState};
+use crate::settings::{self, SettingsSection};
+use crate::suggestions;
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// Editors often write a file in several steps, so changes are applied once it's been quiet
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Config files that are applied while the app is running
+const WATCHED_FILES: &[&str] = &[
+    "user_bangs.json",
+    "keywords.json",
+    "settings.json",
+    "rules.txt",
+    "detectors.json",
+    "suggestions.json",
+    "locale.json",
+    "network.json",
+    "history_config.json",
+    "logging.json",
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigError {
+    pub file: String,
+    pub message: String,
+}
+
+#[derive(Default)]
+pub struct ConfigWatchState {
+    /// Latest error of each file that failed to reload, cleared once it reloads
+    errors: Mutex<BTreeMap<String, String>>,
+    /// Kept alive for as long as the app runs, dropping it stops the watcher
+    watcher: Mutex<Option<Debouncer<RecommendedWatcher>>>,
+}
+
+fn get_config_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("zephyr")
+}
+
+/// Watches the config directory and applies files edited outside the app.
+/// A file that fails to parse or validate leaves the last good config in place.
+pub fn start(app_handle: &AppHandle) -> Result<(), String> {
+    let config_dir = get_config_dir();
+    std::fs::create_dir_all(&config_dir)
+        .map_err(|e| format!("Failed to create config directory: {}", e))?;
+
+    let handler_app_handle = app_handle.clone();
+    let handler_config_dir = config_dir.clone();
+    let mut debouncer = new_debouncer(DEBOUNCE, move |result: DebounceEventResult| match result {
+        Ok(events) => {
+            let files: HashSet<String> = events
+                .iter()
+                .filter_map(|event| event.path.file_name())
+                .map(|name| name.to_string_lossy().to_string())
+                .filter(|name| WATCHED_FILES.contains(&name.as_str()))
+                // Saving from the app triggers events too, those are already applied
+                .filter(|name| !persist::is_own_write(&handler_config_dir.join(name)))
+                .collect();
+
+            for file in files {
+                reload(&handler_app_handle, &file);
+            }
+        }
+        Err(e) => logger::error(&format!("Config watcher error: {}", e)),
+    })
+    .map_err(|e| format!("Failed to create config watcher: {}", e))?;
+
+    debouncer
+        .watcher()
+        .watch(&config_dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Failed to watch {}: {}", config_dir.display(), e))?;
+
+    logger::info(&format!("Watching {} for changes", config_dir.display()));
+    *app_handle
+        .state::<ConfigWatchState>()
+        .watcher
+        .lock()
+        .unwrap() = Some(debouncer);
+    Ok(())
+}
+
+fn reload(app_handle: &AppHandle, file: &str) {
+    let result = match file {
+        "user_bangs.json" => reload_user_bangs(app_handle),
+        "rules.txt" => reload_rules(app_handle),
+        _ => reload_settings(app_handle, file),
+    };
+
+    let state = app_handle.state::<ConfigWatchState>();
+    match result {
+        Ok(()) => {
+            logger::info(&format!("Reloaded {}", file));
+            state.errors.lock().unwrap().remove(file);
+
+            if let Err(e) = app_handle.emit("config-reloaded", file) {
+                logger::error(&format!("Failed to send config reload: {}", e));
+            }
+        }
+        Err(message) => {
+            logger::error(&format!(
+                "Keeping the current config, {} is invalid: {}",
+                file, message
+            ));
+            state
+                .errors
+                .lock()
+                .unwrap()
+                .insert(file.to_string(), message.clone());
+
+            let error = ConfigError {
+                file: file.to_string(),
+                message,
+            };
+            if let Err(e) = app_handle.emit("config-error", &error) {
+                logger::error(&format!("Failed to send config error: {}", e));
+            }
+        }
+    }
+}
+
+fn reload_user_bangs(app_handle: &AppHandle) -> Result<(), String> {
+    let bang_state = app_handle.state::<BangState>();
+    let mut bangs_lock = bang_state.bangs.lock().unwrap();
+    let count = bangs::reload_user_bangs(app_handle, &mut bangs_lock)?;
+
+    logger::info(&format!("Loaded {} custom user bangs", count));
+    Ok(())
+}
+
+/// Reads one of the settings files into its settings group
+fn read_settings_file(
+    app_handle: &AppHandle,
+    file: &str,
+) -> Result<Option<SettingsSection>, String> {
+    let section = match file {
+        "settings.json" => settings::read_settings()?.map(SettingsSection::General),
+        "keywords.json" => Some(SettingsSection::Keywords(bangs::reload_keywords(
+            app_handle,
+        )?)),
+        "detectors.json" => detectors::read_detector_config()?.map(SettingsSection::Detectors),
+        "suggestions.json" => {
+            suggestions::read_suggestion_config()?.map(SettingsSection::Suggestions)
+        }
+        "locale.json" => locale::read_locale_settings()?.map(SettingsSection::Locale),
+        "network.json" => http::read_network_settings()?.map(SettingsSection::Network),
+        "history_config.json" => history::read_config()?.map(SettingsSection::History),
+        "logging.json" => logger::read_privacy_level()?.map(SettingsSection::LogPrivacy),
+        _ => None,
+    };
+
+    Ok(section)
+}
+
+fn reload_settings(app_handle: &AppHandle, file: &str) -> Result<(), String> {
+    // A deleted settings file keeps the settings in use, they're written again on the next change
+    let Some(section) = read_settings_file(app_handle, file)? else {
+        return Ok(());
+    };
+
+    settings::reload_section(app_handle, section)
+}
+
+/// Invalid lines are skipped like at startup, they're listed by `get_routing_rule_errors`
+fn reload_rules(app_handle: &AppHandle) -> Result<(), String> {
+    let rule_set = rules::read_rules()?;
+    rules::log_rule_set(&rule_set);
+    *app_handle.state::<RuleState>().rules.lock().unwrap() = rule_set;
+    Ok(())
+}
+
+/// Config files that failed to reload and are still invalid
+#[tauri::command]
+pub fn get_config_errors(state: State<'_, ConfigWatchState>) -> Vec<ConfigError> {
+    state
+        .errors
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(file, message)| ConfigError {
+            file: file.clone(),
+            message: message.clone(),
+        })
+        .collect()
+}
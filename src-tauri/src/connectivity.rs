@@ -0,0 +1,254 @@
+use crate::http;
+use crate::logger;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{watch, Notify};
+
+/// Lightweight endpoint used to check whether the internet is reachable
+const PROBE_URL: &str = "https://duckduckgo.com/favicon.ico";
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How often connectivity is checked while online
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// First retry delay while offline, doubled after every failed check
+const INITIAL_BACKOFF: Duration = Duration::from_secs(5);
+
+const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ConnectivityStatus {
+    /// Whether network requests should be made
+    pub online: bool,
+    /// Set when the user switched to offline mode from the tray
+    pub manual_offline: bool,
+    /// Result of the last check, `None` until the first one finishes
+    #[serde(skip)]
+    reachable: Option<bool>,
+}
+
+impl ConnectivityStatus {
+    fn new(manual_offline: bool, reachable: Option<bool>) -> Self {
+        Self {
+            // Optimistic until the first check says otherwise
+            online: !manual_offline && reachable != Some(false),
+            manual_offline,
+            reachable,
+        }
+    }
+}
+
+lazy_static! {
+    static ref STATUS: watch::Sender<ConnectivityStatus> =
+        watch::channel(ConnectivityStatus::new(false, None)).0;
+    // Wakes the monitor up to check again before its next scheduled check
+    static ref RECHECK: Notify = Notify::new();
+    // When the last check started, so network errors don't trigger checks back to back
+    static ref LAST_PROBE: Mutex<Option<Instant>> = Mutex::new(None);
+}
+
+/// Set while a check asked for by a network error hasn't started yet
+static RECHECK_PENDING: AtomicBool = AtomicBool::new(false);
+
+pub fn status() -> ConnectivityStatus {
+    *STATUS.borrow()
+}
+
+pub fn is_online() -> bool {
+    status().online
+}
+
+/// Waits for the first connectivity check, so startup doesn't wait on requests timing out
+pub async fn check_online() -> bool {
+    let mut receiver = STATUS.subscribe();
+    let status = receiver
+        .wait_for(|status| status.manual_offline || status.reachable.is_some())
+        .await
+        .map(|status| *status);
+
+    status.map_or(true, |status| status.online)
+}
+
+/// Waits until network requests can be made again
+pub async fn wait_until_online() {
+    let mut receiver = STATUS.subscribe();
+    let _ = receiver.wait_for(|status| status.online).await;
+}
+
+pub fn set_manual_offline(app_handle: &AppHandle, manual_offline: bool) {
+    logger::info(&format!(
+        "Offline mode {}",
+        if manual_offline {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    ));
+
+    update_status(app_handle, |status| {
+        ConnectivityStatus::new(manual_offline, status.reachable)
+    });
+    RECHECK.notify_one();
+}
+
+/// Called when a request failed to connect, so the monitor checks right away
+/// instead of every following request failing too. Errors while a check is pending or
+/// soon after the last one are ignored.
+pub fn report_network_error() {
+    if !recheck_due(
+        status().reachable,
+        *LAST_PROBE.lock().unwrap(),
+        Instant::now(),
+    ) {
+        return;
+    }
+    if !RECHECK_PENDING.swap(true, Ordering::SeqCst) {
+        RECHECK.notify_one();
+    }
+}
+
+/// While offline the monitor is already retrying on its backoff schedule, and right
+/// after a check another one wouldn't tell anything new
+fn recheck_due(reachable: Option<bool>, last_probe: Option<Instant>, now: Instant) -> bool {
+    reachable != Some(false)
+        && last_probe.is_none_or(|started| now.duration_since(started) >= INITIAL_BACKOFF)
+}
+
+/// Delays between checks: the regular interval while online, and exponentially longer
+/// ones while offline
+struct Backoff {
+    next: Duration,
+}
+
+impl Backoff {
+    fn new() -> Self {
+        Self {
+            next: INITIAL_BACKOFF,
+        }
+    }
+
+    /// Returns how long to wait after a check
+    fn after_check(&mut self, reachable: bool) -> Duration {
+        if reachable {
+            self.next = INITIAL_BACKOFF;
+            return CHECK_INTERVAL;
+        }
+
+        let delay = self.next;
+        self.next = (self.next * 2).min(MAX_BACKOFF);
+        delay
+    }
+}
+
+fn update_status(
+    app_handle: &AppHandle,
+    update: impl FnOnce(&ConnectivityStatus) -> ConnectivityStatus,
+) {
+    // The UI only hears about changes to whether it's online, not every check
+    let mut notify_ui = false;
+    STATUS.send_if_modified(|status| {
+        let updated = update(status);
+        notify_ui =
+            updated.online != status.online || updated.manual_offline != status.manual_offline;
+
+        let modified = updated != *status;
+        *status = updated;
+        modified
+    });
+
+    let status = status();
+    if notify_ui {
+        logger::info(&format!(
+            "Connectivity changed: {}",
+            if status.online { "online" } else { "offline" }
+        ));
+
+        if let Err(e) = app_handle.emit("connectivity-changed", status) {
+            logger::error(&format!("Failed to send connectivity status: {}", e));
+        }
+    }
+}
+
+async fn probe() -> bool {
+    http::client()
+        .head(PROBE_URL)
+        .timeout(PROBE_TIMEOUT)
+        .send()
+        .await
+        .is_ok()
+}
+
+/// Checks connectivity in the background, backing off exponentially while offline
+pub fn start_monitor(app_handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut backoff = Backoff::new();
+
+        loop {
+            RECHECK_PENDING.store(false, Ordering::SeqCst);
+
+            // No requests at all while the user has chosen to be offline
+            if status().manual_offline {
+                RECHECK.notified().await;
+                continue;
+            }
+
+            *LAST_PROBE.lock().unwrap() = Some(Instant::now());
+            let reachable = probe().await;
+            update_status(&app_handle, |status| {
+                ConnectivityStatus::new(status.manual_offline, Some(reachable))
+            });
+
+            let delay = backoff.after_check(reachable);
+            if !reachable {
+                logger::debug(&format!("Offline, checking again in {}s", delay.as_secs()));
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = RECHECK.notified() => {}
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_connectivity_status() -> ConnectivityStatus {
+    status()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_while_offline() {
+        let mut backoff = Backoff::new();
+
+        let delays: Vec<u64> = (0..9)
+            .map(|_| backoff.after_check(false).as_secs())
+            .collect();
+        assert_eq!(delays, [5, 10, 20, 40, 80, 160, 300, 300, 300]);
+
+        // Back online, the regular interval applies and the backoff starts over
+        assert_eq!(backoff.after_check(true), CHECK_INTERVAL);
+        assert_eq!(backoff.after_check(false), INITIAL_BACKOFF);
+    }
+
+    #[test]
+    fn rechecks_on_errors_only_when_due() {
+        let now = Instant::now();
+        let just_now = Some(now - Duration::from_secs(1));
+        let a_while_ago = Some(now - Duration::from_secs(30));
+
+        assert!(recheck_due(None, None, now));
+        assert!(recheck_due(Some(true), a_while_ago, now));
+        assert!(!recheck_due(Some(true), just_now, now));
+        // Offline, the backoff schedule decides when to check
+        assert!(!recheck_due(Some(false), a_while_ago, now));
+    }
+}
@@ -0,0 +1,276 @@
+use crate::detectors::config::{DetectorConfig, DetectorSettings};
+use crate::detectors::pipeline::{fill_template, Detector};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref EMAIL: Regex =
+        Regex::new(r"^(?i:mailto:)?(?P<email>[^\s@/:]+@[^\s@/:]+\.[A-Za-z]{2,})$").unwrap();
+    // Only unambiguous formats, so dates and plain numbers aren't mistaken for phone numbers
+    static ref PHONE: Regex = Regex::new(
+        r"^(?i:tel:)?(?P<phone>\+[0-9][0-9 ().\-]{5,18}[0-9]|\([0-9]{3}\) ?[0-9]{3}-[0-9]{4})$"
+    )
+    .unwrap();
+    static ref CVE: Regex = Regex::new(r"^(?i)(?P<id>CVE-[0-9]{4}-[0-9]{4,})$").unwrap();
+    static ref DOI: Regex = Regex::new(r"^(?i:doi:\s*)?(?P<doi>10\.[0-9]{4,9}/\S+)$").unwrap();
+    // At least two letters in the project key, so versions like `X-1` don't match
+    static ref ISSUE_KEY: Regex = Regex::new(
+        r"^\b(?P<key>(?P<project>[A-Z][A-Z0-9_]*[A-Z][A-Z0-9_]*)-(?P<number>[0-9]+))\b$"
+    )
+    .unwrap();
+    static ref GITHUB_ISSUE: Regex = Regex::new(
+        r"^(?P<owner>[A-Za-z0-9][A-Za-z0-9-]*)/(?P<repo>[A-Za-z0-9._-]+)#(?P<number>[0-9]+)$"
+    )
+    .unwrap();
+    static ref GIT_SHA: Regex = Regex::new(r"^(?P<sha>[0-9a-f]{7,40})$").unwrap();
+}
+
+/// Names of standards and the like that look just like issue keys
+const NOT_ISSUE_PROJECTS: &[&str] = &[
+    "AES", "ASCII", "COVID", "CVE", "ECMA", "IEC", "IEEE", "ISO", "PEP", "RFC", "RSA", "SHA",
+    "TLS", "UCS", "UTF",
+];
+
+fn is_issue_key(input: &str) -> bool {
+    input
+        .split_once('-')
+        .is_some_and(|(project, _)| !NOT_ISSUE_PROJECTS.contains(&project))
+}
+
+/// Hashes need a letter and a digit, so words like "defaced" and plain numbers aren't
+/// treated as commits
+fn is_git_sha(input: &str) -> bool {
+    input.chars().any(|c| c.is_ascii_digit()) && input.chars().any(|c| c.is_ascii_alphabetic())
+}
+
+pub(crate) fn default_email() -> DetectorSettings {
+    DetectorSettings::new(true, "mailto:{{email}}")
+}
+
+pub(crate) fn default_phone() -> DetectorSettings {
+    DetectorSettings::new(true, "tel:{{phone}}")
+}
+
+pub(crate) fn default_cve() -> DetectorSettings {
+    DetectorSettings::new(true, "https://nvd.nist.gov/vuln/detail/{{id}}")
+}
+
+pub(crate) fn default_doi() -> DetectorSettings {
+    DetectorSettings::new(true, "https://doi.org/{{doi}}")
+}
+
+pub(crate) fn default_issue_key() -> DetectorSettings {
+    // Needs the team's tracker URL, e.g. https://jira.example.com/browse/{{key}}
+    DetectorSettings::new(false, "")
+}
+
+pub(crate) fn default_github_issue() -> DetectorSettings {
+    DetectorSettings::new(
+        true,
+        "https://github.com/{{owner}}/{{repo}}/issues/{{number}}",
+    )
+}
+
+pub(crate) fn default_git_sha() -> DetectorSettings {
+    DetectorSettings::new(true, "https://github.com/search?q={{sha}}&type=commits")
+}
+
+/// A detector that matches the whole input against a pattern and fills a URL template
+/// with its capture groups
+pub struct PatternDetector {
+    id: &'static str,
+    pattern: &'static Regex,
+    settings: DetectorSettings,
+    accept: fn(&str) -> bool,
+}
+
+impl PatternDetector {
+    pub fn new(id: &'static str, pattern: &'static Regex, settings: &DetectorSettings) -> Self {
+        Self {
+            id,
+            pattern,
+            settings: settings.clone(),
+            accept: |_| true,
+        }
+    }
+
+    /// Adds an extra check on the input for patterns that are too loose on their own
+    pub fn accept(mut self, accept: fn(&str) -> bool) -> Self {
+        self.accept = accept;
+        self
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.settings.enabled && !self.settings.url.is_empty()
+    }
+}
+
+impl Detector for PatternDetector {
+    fn id(&self) -> &str {
+        self.id
+    }
+
+    fn detect(&self, input: &str) -> Option<String> {
+        if !(self.accept)(input) {
+            return None;
+        }
+
+        self.pattern
+            .captures(input)
+            .map(|captures| fill_template(&self.settings.url, &captures))
+    }
+}
+
+/// Builds the enabled built-in detectors in priority order
+pub(crate) fn from_config(config: &DetectorConfig) -> Vec<Box<dyn Detector>> {
+    let detectors = [
+        PatternDetector::new("email", &EMAIL, &config.email),
+        PatternDetector::new("phone", &PHONE, &config.phone),
+        PatternDetector::new("cve", &CVE, &config.cve),
+        PatternDetector::new("doi", &DOI, &config.doi),
+        PatternDetector::new("github_issue", &GITHUB_ISSUE, &config.github_issue),
+        PatternDetector::new("issue_key", &ISSUE_KEY, &config.issue_key).accept(is_issue_key),
+        PatternDetector::new("git_sha", &GIT_SHA, &config.git_sha).accept(is_git_sha),
+    ];
+
+    detectors
+        .into_iter()
+        .filter(|detector| detector.is_enabled())
+        .map(|detector| Box::new(detector) as Box<dyn Detector>)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The detector that recognizes the input with every built-in detector enabled,
+    /// and the URL it leads to
+    fn detect(input: &str) -> Option<(String, String)> {
+        let config = DetectorConfig {
+            issue_key: DetectorSettings::new(true, "https://jira.example.com/browse/{{key}}"),
+            ..Default::default()
+        };
+
+        from_config(&config).iter().find_map(|detector| {
+            detector
+                .detect(input)
+                .map(|url| (detector.id().to_string(), url))
+        })
+    }
+
+    fn detected_by(input: &str, id: &str) -> Option<String> {
+        detect(input)
+            .filter(|(detector, _)| detector == id)
+            .map(|(_, url)| url)
+    }
+
+    #[test]
+    fn detects_email_addresses() {
+        assert_eq!(
+            detected_by("jane.doe@example.com", "email").as_deref(),
+            Some("mailto:jane.doe%40example.com")
+        );
+        assert_eq!(
+            detected_by("MAILTO:jane@example.org", "email").as_deref(),
+            Some("mailto:jane%40example.org")
+        );
+        assert_eq!(detect("jane@localhost"), None);
+        assert_eq!(detect("https://a@example.com/x"), None);
+    }
+
+    #[test]
+    fn detects_unambiguous_phone_numbers() {
+        assert_eq!(
+            detected_by("+1 555 123 4567", "phone").as_deref(),
+            Some("tel:%2B1%20555%20123%204567")
+        );
+        assert_eq!(
+            detected_by("(555) 123-4567", "phone").as_deref(),
+            Some("tel:%28555%29%20123-4567")
+        );
+        assert_eq!(detect("2024-01-15"), None);
+        assert_eq!(detect("5551234567"), None);
+    }
+
+    #[test]
+    fn detects_cves() {
+        assert_eq!(
+            detected_by("CVE-2021-44228", "cve").as_deref(),
+            Some("https://nvd.nist.gov/vuln/detail/CVE-2021-44228")
+        );
+        assert_eq!(
+            detected_by("cve-2014-0160", "cve").as_deref(),
+            Some("https://nvd.nist.gov/vuln/detail/cve-2014-0160")
+        );
+        assert_eq!(detect("CVE-21-1"), None);
+    }
+
+    #[test]
+    fn detects_dois() {
+        assert_eq!(
+            detected_by("10.1038/nphys1170", "doi").as_deref(),
+            Some("https://doi.org/10.1038%2Fnphys1170")
+        );
+        assert_eq!(
+            detected_by("doi: 10.1000/xyz?a=b", "doi").as_deref(),
+            Some("https://doi.org/10.1000%2Fxyz%3Fa%3Db")
+        );
+        assert_eq!(detect("10.1/short"), None);
+    }
+
+    #[test]
+    fn detects_issue_keys() {
+        assert_eq!(
+            detected_by("PROJ-123", "issue_key").as_deref(),
+            Some("https://jira.example.com/browse/PROJ-123")
+        );
+        assert_eq!(
+            detected_by("AB2-7", "issue_key").as_deref(),
+            Some("https://jira.example.com/browse/AB2-7")
+        );
+
+        for input in [
+            "COVID-19", "UTF-8", "ISO-8601", "X-1", "A1-2", "proj-123", "PROJ-",
+        ] {
+            assert_eq!(detect(input), None, "{}", input);
+        }
+    }
+
+    #[test]
+    fn detects_github_issues() {
+        assert_eq!(
+            detected_by("tauri-apps/tauri#1234", "github_issue").as_deref(),
+            Some("https://github.com/tauri-apps/tauri/issues/1234")
+        );
+        assert_eq!(detect("tauri#1234"), None);
+        assert_eq!(detect("a/b#x"), None);
+    }
+
+    #[test]
+    fn detects_git_shas() {
+        assert_eq!(
+            detected_by("3f2a9c1", "git_sha").as_deref(),
+            Some("https://github.com/search?q=3f2a9c1&type=commits")
+        );
+        assert!(detected_by("e83c5163316f89bfbde7d9ab23ca2e25604af290", "git_sha").is_some());
+
+        let too_long = "e83c5163316f89bfbde7d9ab23ca2e25604af2901";
+        for input in ["3f2a9c", "1234567", "defaced", "3F2A9C1", too_long] {
+            assert_eq!(detect(input), None, "{}", input);
+        }
+    }
+
+    #[test]
+    fn skips_disabled_detectors_and_those_without_url() {
+        let ids: Vec<String> = from_config(&DetectorConfig::default())
+            .iter()
+            .map(|detector| detector.id().to_string())
+            .collect();
+
+        assert_eq!(
+            ids,
+            ["email", "phone", "cve", "doi", "github_issue", "git_sha"]
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use crate::detectors::builtin;
+use crate::logger;
+use crate::persist::{self, Schema};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetectorSettings {
+    pub enabled: bool,
+    /// URL template filled with the detector's capture groups, e.g. `{{id}}`
+    pub url: String,
+}
+
+impl DetectorSettings {
+    pub(crate) fn new(enabled: bool, url: &str) -> Self {
+        Self {
+            enabled,
+            url: url.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetectorConfig {
+    #[serde(default = "builtin::default_email")]
+    pub email: DetectorSettings,
+    #[serde(default = "builtin::default_phone")]
+    pub phone: DetectorSettings,
+    #[serde(default = "builtin::default_cve")]
+    pub cve: DetectorSettings,
+    #[serde(default = "builtin::default_doi")]
+    pub doi: DetectorSettings,
+    #[serde(default = "builtin::default_issue_key")]
+    pub issue_key: DetectorSettings,
+    #[serde(default = "builtin::default_github_issue")]
+    pub github_issue: DetectorSettings,
+    #[serde(default = "builtin::default_git_sha")]
+    pub git_sha: DetectorSettings,
+}
+
+impl Default for DetectorConfig {
+    fn default() -> Self {
+        Self {
+            email: builtin::default_email(),
+            phone: builtin::default_phone(),
+            cve: builtin::default_cve(),
+            doi: builtin::default_doi(),
+            issue_key: builtin::default_issue_key(),
+            github_issue: builtin::default_github_issue(),
+            git_sha: builtin::default_git_sha(),
+        }
+    }
+}
+
+/// Version 1 added the versioned envelope
+const CONFIG_SCHEMA: Schema = Schema::new(&[persist::unchanged]);
+
+pub(crate) fn get_config_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("zephyr")
+        .join("detectors.json")
+}
+
+pub fn load_detector_config() -> DetectorConfig {
+    match persist::read(&get_config_path(), &CONFIG_SCHEMA) {
+        Ok(config) => config.unwrap_or_default(),
+        Err(e) => {
+            logger::error(&format!("Failed to load detector config: {}", e));
+            DetectorConfig::default()
+        }
+    }
+}
+
+/// Reads the config after it was edited by hand, reporting errors instead of recovering a backup
+pub fn read_detector_config() -> Result<Option<DetectorConfig>, String> {
+    persist::read_without_recovery(&get_config_path(), &CONFIG_SCHEMA)
+}
+
+pub fn save_detector_config(config: &DetectorConfig) -> Result<(), String> {
+    persist::write(&get_config_path(), &CONFIG_SCHEMA, config)
+}
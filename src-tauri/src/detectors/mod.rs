@@ -0,0 +1,8 @@
+mod builtin;
+mod config;
+mod pipeline;
+
+pub use config::{
+    load_detector_config, read_detector_config, save_detector_config, DetectorConfig,
+};
+pub use pipeline::{fill_template, DetectorPipeline};
@@ -0,0 +1,106 @@
+use crate::detectors::builtin;
+use crate::detectors::config::DetectorConfig;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde::Serialize;
+
+lazy_static! {
+    static ref PLACEHOLDER: Regex = Regex::new(r"\{\{(\w+)\}\}").unwrap();
+}
+
+/// A recognized piece of special input and where it should take the user
+#[derive(Debug, Clone, Serialize)]
+pub struct Detection {
+    pub detector: String,
+    pub url: String,
+}
+
+pub trait Detector: Send + Sync {
+    /// Identifier of the detector, matching its key in the config file
+    fn id(&self) -> &str;
+
+    /// Returns the URL to open if the whole input is recognized
+    fn detect(&self, input: &str) -> Option<String>;
+}
+
+/// Ordered list of detectors that get a chance to handle input before bangs
+pub struct DetectorPipeline {
+    detectors: Vec<Box<dyn Detector>>,
+}
+
+impl DetectorPipeline {
+    pub fn new(detectors: Vec<Box<dyn Detector>>) -> Self {
+        Self { detectors }
+    }
+
+    pub fn from_config(config: &DetectorConfig) -> Self {
+        Self::new(builtin::from_config(config))
+    }
+
+    /// Runs the input through each detector in order, returning the first match
+    pub fn detect(&self, input: &str) -> Option<Detection> {
+        let input = input.trim();
+
+        self.detectors.iter().find_map(|detector| {
+            detector.detect(input).map(|url| Detection {
+                detector: detector.id().to_string(),
+                url,
+            })
+        })
+    }
+}
+
+impl Default for DetectorPipeline {
+    fn default() -> Self {
+        Self::from_config(&DetectorConfig::default())
+    }
+}
+
+/// Replaces `{{0}}`, `{{1}}`, ... and `{{name}}` placeholders with the matching capture groups,
+/// URL-encoded. Unknown placeholders are replaced with an empty string.
+pub fn fill_template(template: &str, captures: &Captures) -> String {
+    PLACEHOLDER
+        .replace_all(template, |placeholder: &Captures| {
+            let key = &placeholder[1];
+            let group = match key.parse::<usize>() {
+                Ok(index) => captures.get(index),
+                Err(_) => captures.name(key),
+            };
+            group
+                .map(|m| urlencoding::encode(m.as_str()).into_owned())
+                .unwrap_or_default()
+        })
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_numbered_and_named_placeholders_encoded() {
+        let pattern = Regex::new(r"^(?P<owner>\S+) (\S+)$").unwrap();
+        let captures = pattern.captures("a&b c/d").unwrap();
+
+        assert_eq!(
+            fill_template(
+                "https://x.test/{{owner}}/{{2}}?all={{0}}&none={{nope}}",
+                &captures
+            ),
+            "https://x.test/a%26b/c%2Fd?all=a%26b%20c%2Fd&none="
+        );
+    }
+
+    #[test]
+    fn returns_the_first_matching_detector() {
+        let pipeline = DetectorPipeline::default();
+
+        let detection = pipeline.detect("  CVE-2021-44228 ").unwrap();
+        assert_eq!(detection.detector, "cve");
+        assert_eq!(
+            detection.url,
+            "https://nvd.nist.gov/vuln/detail/CVE-2021-44228"
+        );
+        assert!(pipeline.detect("rust tutorial").is_none());
+    }
+}
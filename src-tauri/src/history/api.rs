@@ -0,0 +1,598 @@
+use crate::history::crypto::load_cipher;
+use crate::history::models::{
+    HistoryConfig, HistoryEncryption, HistoryEntry, HistoryRecord, SearchHistory, MAX_RECENT_VISITS,
+};
+use crate::history::storage::{
+    append_record, load_config, load_records, rewrite_records, save_config,
+};
+use crate::logger;
+use chrono::{DateTime, Duration, Utc};
+use regex::Regex;
+
+pub fn load_history() -> SearchHistory {
+    let mut history = SearchHistory {
+        config: load_config(),
+        ..Default::default()
+    };
+
+    history.exclusions =
+        compile_exclusions(&history.config.excluded_patterns).unwrap_or_else(|e| {
+            logger::error(&e);
+            Vec::new()
+        });
+
+    history.cipher = load_cipher(history.config.encryption).unwrap_or_else(|e| {
+        logger::error(&e);
+        None
+    });
+
+    let loaded = load_records(history.cipher.as_ref());
+    history.undecryptable = loaded.undecryptable;
+    for record in &loaded.records {
+        apply_record(&mut history, record);
+    }
+
+    let pruned = apply_retention(&mut history);
+
+    // Never rewrite the log while some of it can't be read, or those records would be lost
+    if (loaded.format_changed || pruned) && can_rewrite(&history).is_ok() {
+        if let Err(e) = rewrite_records(&to_records(&history), history.cipher.as_ref()) {
+            logger::error(&format!("Failed to compact search history: {}", e));
+        }
+    }
+
+    logger::info(&format!(
+        "Loaded {} search history entries",
+        history.entries.len()
+    ));
+    history
+}
+
+fn compile_exclusions(patterns: &[String]) -> Result<Vec<Regex>, String> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            Regex::new(pattern)
+                .map_err(|e| format!("Invalid history exclusion pattern '{}': {}", pattern, e))
+        })
+        .collect()
+}
+
+/// Encrypted history is only written once its key is available
+fn can_write(history: &SearchHistory) -> bool {
+    history.config.encryption == HistoryEncryption::None || history.cipher.is_some()
+}
+
+/// Rewriting the log while some of it can't be read would lose those records
+fn can_rewrite(history: &SearchHistory) -> Result<(), String> {
+    if !can_write(history) {
+        return Err("History encryption key is unavailable".to_string());
+    }
+    if history.undecryptable > 0 {
+        return Err(format!(
+            "{} history records can't be decrypted, so the history can't be rewritten",
+            history.undecryptable
+        ));
+    }
+    Ok(())
+}
+
+pub fn update_config(history: &mut SearchHistory, config: HistoryConfig) -> Result<(), String> {
+    apply_config(history, config, save_config)
+}
+
+/// Applies a config read from its file without saving it again
+pub fn reload_config(history: &mut SearchHistory, config: HistoryConfig) -> Result<(), String> {
+    apply_config(history, config, |_| Ok(()))
+}
+
+fn apply_config(
+    history: &mut SearchHistory,
+    config: HistoryConfig,
+    save: impl FnOnce(&HistoryConfig) -> Result<(), String>,
+) -> Result<(), String> {
+    let exclusions = compile_exclusions(&config.excluded_patterns)?;
+
+    // Checked before loading the new key, so a refused change doesn't create one
+    let encryption_changed = config.encryption != history.config.encryption;
+    if encryption_changed {
+        can_rewrite(history)?;
+    }
+
+    let cipher = load_cipher(config.encryption)?;
+
+    save(&config)?;
+
+    history.config = config;
+    history.exclusions = exclusions;
+    history.cipher = cipher;
+
+    let pruned = apply_retention(history);
+    if encryption_changed {
+        rewrite_records(&to_records(history), history.cipher.as_ref())?;
+    } else if pruned {
+        // Pruned entries are dropped from the log once all of it can be read again
+        match can_rewrite(history) {
+            Ok(()) => rewrite_records(&to_records(history), history.cipher.as_ref())?,
+            Err(e) => logger::warn(&e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the query without the incognito prefix, if it has one
+pub fn strip_incognito_prefix(history: &SearchHistory, query: &str) -> Option<String> {
+    let prefix = &history.config.incognito_prefix;
+    if prefix.is_empty() {
+        return None;
+    }
+
+    query
+        .trim_start()
+        .strip_prefix(prefix.as_str())
+        .map(|rest| rest.trim().to_string())
+}
+
+fn is_excluded(history: &SearchHistory, query: &str) -> bool {
+    let excluded_bang = |token: &str| history.config.excluded_bangs.iter().any(|b| b == token);
+
+    // Explicit bangs anywhere in the query, or a keyword as the first word
+    let uses_excluded_bang = query
+        .split('!')
+        .skip(1)
+        .filter_map(|part| part.split_whitespace().next())
+        .any(excluded_bang)
+        || query.split_whitespace().next().is_some_and(excluded_bang);
+
+    uses_excluded_bang || history.exclusions.iter().any(|re| re.is_match(query))
+}
+
+/// Drops entries past the configured age and count limits, returning whether any were removed
+fn apply_retention(history: &mut SearchHistory) -> bool {
+    let initial_count = history.entries.len();
+
+    if let Some(max_age_days) = history.config.max_age_days {
+        let cutoff = Utc::now() - Duration::days(max_age_days as i64);
+        history
+            .entries
+            .retain(|_, entry| entry.last_visited >= cutoff);
+    }
+
+    if let Some(max_entries) = history.config.max_entries {
+        if history.entries.len() > max_entries {
+            let mut last_visits: Vec<DateTime<Utc>> =
+                history.entries.values().map(|e| e.last_visited).collect();
+            last_visits.sort_unstable_by(|a, b| b.cmp(a));
+            let cutoff = last_visits[max_entries];
+
+            history
+                .entries
+                .retain(|_, entry| entry.last_visited > cutoff);
+        }
+    }
+
+    history.entries.len() != initial_count
+}
+
+fn apply_record(history: &mut SearchHistory, record: &HistoryRecord) {
+    let visited_at = DateTime::<Utc>::from_timestamp(record.timestamp, 0).unwrap_or_else(Utc::now);
+
+    let entry = history
+        .entries
+        .entry(record.query.clone())
+        .or_insert_with(|| HistoryEntry {
+            query: record.query.clone(),
+            url: record.url.clone(),
+            visit_count: 0,
+            last_visited: visited_at,
+            frecency: 0.0,
+            recent_visits: Vec::new(),
+        });
+
+    entry.url = record.url.clone();
+    entry.visit_count += 1;
+    entry.last_visited = entry.last_visited.max(visited_at);
+    entry.recent_visits.push(visited_at);
+
+    if entry.recent_visits.len() > MAX_RECENT_VISITS {
+        entry.recent_visits.remove(0);
+    }
+}
+
+pub fn record_search(history: &mut SearchHistory, query: &str, url: &str) -> Result<(), String> {
+    let query = query.trim();
+    // Incognito queries are normally searched without recording them, this is a safety net
+    let incognito = history.incognito || strip_incognito_prefix(history, query).is_some();
+    if query.is_empty() || incognito || is_excluded(history, query) {
+        return Ok(());
+    }
+
+    if !can_write(history) {
+        return Err("History encryption key is unavailable".to_string());
+    }
+
+    let record = HistoryRecord {
+        query: query.to_string(),
+        url: url.to_string(),
+        timestamp: Utc::now().timestamp(),
+    };
+
+    apply_record(history, &record);
+
+    if apply_retention(history) && can_rewrite(history).is_ok() {
+        return rewrite_records(&to_records(history), history.cipher.as_ref());
+    }
+
+    append_record(&record, history.cipher.as_ref())
+}
+
+/// Weight of a visit by age, based on Firefox's frecency buckets
+fn visit_weight(now: DateTime<Utc>, visited_at: DateTime<Utc>) -> f64 {
+    match (now - visited_at).num_days() {
+        ..=4 => 100.0,
+        5..=14 => 70.0,
+        15..=31 => 50.0,
+        32..=90 => 30.0,
+        _ => 10.0,
+    }
+}
+
+/// Total visits scaled by the average weight of the most recent ones
+fn frecency(entry: &HistoryEntry, now: DateTime<Utc>) -> f64 {
+    if entry.recent_visits.is_empty() {
+        return 0.0;
+    }
+
+    let total_weight: f64 = entry
+        .recent_visits
+        .iter()
+        .map(|visited_at| visit_weight(now, *visited_at))
+        .sum();
+
+    entry.visit_count as f64 * total_weight / entry.recent_visits.len() as f64
+}
+
+/// Returns entries whose query starts with the prefix, highest frecency first
+pub fn query_history(history: &SearchHistory, prefix: &str, limit: usize) -> Vec<HistoryEntry> {
+    let now = Utc::now();
+    let prefix = prefix.trim().to_lowercase();
+
+    let mut matches: Vec<HistoryEntry> = history
+        .entries
+        .values()
+        .filter(|entry| entry.query.to_lowercase().starts_with(&prefix))
+        .map(|entry| HistoryEntry {
+            frecency: frecency(entry, now),
+            ..entry.clone()
+        })
+        .collect();
+
+    matches.sort_by(|a, b| {
+        b.frecency
+            .total_cmp(&a.frecency)
+            .then_with(|| b.last_visited.cmp(&a.last_visited))
+    });
+    matches.truncate(limit);
+    matches
+}
+
+fn to_records(history: &SearchHistory) -> Vec<HistoryRecord> {
+    let mut records: Vec<HistoryRecord> = history
+        .entries
+        .values()
+        .flat_map(|entry| {
+            // Visits older than the recent ones only count towards the total
+            let older_visits = entry.visit_count as usize - entry.recent_visits.len();
+            let oldest = entry
+                .recent_visits
+                .first()
+                .copied()
+                .unwrap_or(entry.last_visited);
+
+            std::iter::repeat_n(oldest, older_visits)
+                .chain(entry.recent_visits.iter().copied())
+                .map(|visited_at| HistoryRecord {
+                    query: entry.query.clone(),
+                    url: entry.url.clone(),
+                    timestamp: visited_at.timestamp(),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    records.sort_by_key(|record| record.timestamp);
+    records
+}
+
+pub fn delete_entry(history: &mut SearchHistory, query: &str) -> Result<(), String> {
+    can_rewrite(history)?;
+
+    if history.entries.remove(query).is_none() {
+        return Err(format!("History entry not found: '{}'", query));
+    }
+
+    rewrite_records(&to_records(history), history.cipher.as_ref())
+}
+
+pub fn clear_history(history: &mut SearchHistory) -> Result<(), String> {
+    history.entries.clear();
+    // Clearing drops the records that couldn't be read too
+    rewrite_records(&[], history.cipher.as_ref())?;
+    history.undecryptable = 0;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn days_ago(days: i64) -> DateTime<Utc> {
+        Utc::now() - Duration::days(days)
+    }
+
+    /// An entry visited the given numbers of days ago, oldest first
+    fn entry(query: &str, visits: &[i64]) -> HistoryEntry {
+        let recent_visits: Vec<DateTime<Utc>> = visits.iter().map(|days| days_ago(*days)).collect();
+        HistoryEntry {
+            query: query.to_string(),
+            url: format!("https://example.com/?q={}", query),
+            visit_count: visits.len() as u32,
+            last_visited: *recent_visits.iter().max().unwrap(),
+            frecency: 0.0,
+            recent_visits,
+        }
+    }
+
+    fn history(entries: Vec<HistoryEntry>, config: HistoryConfig) -> SearchHistory {
+        SearchHistory {
+            entries: entries
+                .into_iter()
+                .map(|entry| (entry.query.clone(), entry))
+                .collect(),
+            exclusions: compile_exclusions(&config.excluded_patterns).unwrap(),
+            config,
+            ..Default::default()
+        }
+    }
+
+    fn queries(entries: &[HistoryEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.query.as_str()).collect()
+    }
+
+    #[test]
+    fn refuses_to_rewrite_with_undecryptable_records() {
+        let mut history = history(vec![entry("rust", &[1])], HistoryConfig::default());
+        history.undecryptable = 2;
+
+        assert!(can_rewrite(&history).is_err());
+        assert_eq!(
+            delete_entry(&mut history, "rust"),
+            Err(
+                "2 history records can't be decrypted, so the history can't be rewritten"
+                    .to_string()
+            )
+        );
+        assert!(history.entries.contains_key("rust"));
+
+        // Nor change how the records are stored
+        let config = HistoryConfig {
+            encryption: HistoryEncryption::KeyFile,
+            ..Default::default()
+        };
+        assert!(reload_config(&mut history, config).is_err());
+        assert_eq!(history.config.encryption, HistoryEncryption::None);
+    }
+
+    #[test]
+    fn refuses_to_write_without_the_key() {
+        let mut history = history(
+            Vec::new(),
+            HistoryConfig {
+                encryption: HistoryEncryption::Keyring,
+                ..Default::default()
+            },
+        );
+
+        assert!(!can_write(&history));
+        assert!(can_rewrite(&history).is_err());
+        assert!(record_search(&mut history, "rust", "https://example.com").is_err());
+        assert!(history.entries.is_empty());
+    }
+
+    #[test]
+    fn weighs_visits_by_age_bucket() {
+        let now = Utc::now();
+        let weight = |days| visit_weight(now, now - Duration::days(days));
+
+        assert_eq!(weight(0), 100.0);
+        assert_eq!(weight(4), 100.0);
+        assert_eq!(weight(5), 70.0);
+        assert_eq!(weight(14), 70.0);
+        assert_eq!(weight(15), 50.0);
+        assert_eq!(weight(31), 50.0);
+        assert_eq!(weight(32), 30.0);
+        assert_eq!(weight(90), 30.0);
+        assert_eq!(weight(91), 10.0);
+    }
+
+    #[test]
+    fn ranks_matches_by_frecency() {
+        let history = history(
+            vec![
+                // One recent visit outweighs two old ones
+                entry("rust book", &[100, 100]),
+                entry("rust lang", &[1]),
+                entry("rustup", &[10, 2, 1]),
+                entry("python", &[1, 1, 1, 1]),
+            ],
+            HistoryConfig::default(),
+        );
+
+        let matches = query_history(&history, "rust", 10);
+
+        assert_eq!(queries(&matches), ["rustup", "rust lang", "rust book"]);
+        assert_eq!(matches[1].frecency, 100.0);
+        assert_eq!(matches[2].frecency, 20.0);
+        assert_eq!(queries(&query_history(&history, "rust", 1)), ["rustup"]);
+    }
+
+    #[test]
+    fn breaks_frecency_ties_by_last_visit() {
+        let history = history(
+            vec![entry("rust book", &[3]), entry("rust lang", &[1])],
+            HistoryConfig::default(),
+        );
+
+        let matches = query_history(&history, "rust", 10);
+
+        assert_eq!(queries(&matches), ["rust lang", "rust book"]);
+    }
+
+    #[test]
+    fn matches_the_start_of_queries_ignoring_case() {
+        let history = history(
+            vec![
+                entry("Rust book", &[1]),
+                entry("learn rust", &[1]),
+                entry("rustup", &[2]),
+            ],
+            HistoryConfig::default(),
+        );
+
+        assert_eq!(
+            queries(&query_history(&history, " rUST ", 10)),
+            ["Rust book", "rustup"]
+        );
+        assert_eq!(
+            queries(&query_history(&history, "learn r", 10)),
+            ["learn rust"]
+        );
+        assert!(query_history(&history, "go", 10).is_empty());
+    }
+
+    #[test]
+    fn drops_entries_past_the_max_age() {
+        let mut history = history(
+            vec![entry("recent", &[40, 29]), entry("old", &[31])],
+            HistoryConfig {
+                max_age_days: Some(30),
+                ..Default::default()
+            },
+        );
+
+        assert!(apply_retention(&mut history));
+        assert_eq!(history.entries.keys().collect::<Vec<_>>(), ["recent"]);
+        assert!(!apply_retention(&mut history));
+    }
+
+    #[test]
+    fn keeps_the_most_recently_visited_entries() {
+        let mut history = history(
+            vec![entry("a", &[1]), entry("b", &[2]), entry("c", &[3])],
+            HistoryConfig {
+                max_entries: Some(2),
+                ..Default::default()
+            },
+        );
+
+        assert!(apply_retention(&mut history));
+
+        let mut kept: Vec<&String> = history.entries.keys().collect();
+        kept.sort();
+        assert_eq!(kept, ["a", "b"]);
+    }
+
+    #[test]
+    fn drops_every_entry_tied_at_the_max_count() {
+        let tied = days_ago(2);
+        let mut history = history(
+            vec![entry("a", &[1]), entry("b", &[2]), entry("c", &[3])],
+            HistoryConfig {
+                max_entries: Some(2),
+                ..Default::default()
+            },
+        );
+        for query in ["b", "c"] {
+            history.entries.get_mut(query).unwrap().last_visited = tied;
+        }
+
+        // Which of the tied entries would stay would depend on the map's order
+        assert!(apply_retention(&mut history));
+        assert_eq!(history.entries.keys().collect::<Vec<_>>(), ["a"]);
+    }
+
+    #[test]
+    fn excludes_bangs_and_keywords() {
+        let history = history(
+            Vec::new(),
+            HistoryConfig {
+                excluded_bangs: vec!["bank".to_string()],
+                ..Default::default()
+            },
+        );
+
+        assert!(is_excluded(&history, "!bank balance"));
+        assert!(is_excluded(&history, "balance !bank"));
+        assert!(is_excluded(&history, "bank balance"));
+        assert!(!is_excluded(&history, "!banking balance"));
+        assert!(!is_excluded(&history, "river bank"));
+    }
+
+    #[test]
+    fn excludes_queries_matching_patterns() {
+        let history = history(
+            Vec::new(),
+            HistoryConfig {
+                excluded_patterns: vec![r"^\d{4} \d{4}".to_string(), "(?i)password".to_string()],
+                ..Default::default()
+            },
+        );
+
+        assert!(is_excluded(&history, "1234 5678 9012"));
+        assert!(is_excluded(&history, "reset PASSWORD"));
+        assert!(!is_excluded(&history, "card 1234 5678"));
+        assert!(compile_exclusions(&["(".to_string()]).is_err());
+    }
+
+    #[test]
+    fn does_not_record_excluded_queries() {
+        let mut history = history(
+            Vec::new(),
+            HistoryConfig {
+                excluded_bangs: vec!["bank".to_string()],
+                ..Default::default()
+            },
+        );
+
+        record_search(&mut history, "!bank balance", "https://bank.example").unwrap();
+
+        assert!(history.entries.is_empty());
+    }
+
+    #[test]
+    fn strips_the_incognito_prefix() {
+        let mut history = history(Vec::new(), HistoryConfig::default());
+
+        assert_eq!(
+            strip_incognito_prefix(&history, "  * gift ideas "),
+            Some("gift ideas".to_string())
+        );
+        assert_eq!(strip_incognito_prefix(&history, "gift * ideas"), None);
+
+        history.config.incognito_prefix = String::new();
+        assert_eq!(strip_incognito_prefix(&history, "* gift ideas"), None);
+    }
+
+    #[test]
+    fn does_not_record_incognito_queries() {
+        let mut history = history(Vec::new(), HistoryConfig::default());
+
+        record_search(&mut history, "*gift ideas", "https://example.com").unwrap();
+        assert!(history.entries.is_empty());
+
+        history.incognito = true;
+        record_search(&mut history, "gift ideas", "https://example.com").unwrap();
+        assert!(history.entries.is_empty());
+    }
+}
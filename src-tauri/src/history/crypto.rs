@@ -0,0 +1,200 @@
+use crate::history::models::HistoryEncryption;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const KEYRING_SERVICE: &str = "zephyr";
+const KEYRING_USER: &str = "history-encryption-key";
+const NONCE_LEN: usize = 12;
+
+pub(crate) fn get_key_file_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("zephyr")
+        .join("history.key")
+}
+
+/// Loads the history key from its configured location, generating one on first use
+pub(crate) fn load_cipher(encryption: HistoryEncryption) -> Result<Option<Aes256Gcm>, String> {
+    let stored_key = match encryption {
+        HistoryEncryption::None => return Ok(None),
+        HistoryEncryption::Keyring => read_keyring_key()?,
+        HistoryEncryption::KeyFile => read_key_file()?,
+    };
+
+    let key = match stored_key {
+        Some(encoded) => decode_key(&encoded)?,
+        None => {
+            let key = Aes256Gcm::generate_key(OsRng);
+            let encoded = STANDARD.encode(key);
+
+            match encryption {
+                HistoryEncryption::Keyring => write_keyring_key(&encoded)?,
+                _ => write_key_file(&encoded)?,
+            }
+
+            key
+        }
+    };
+
+    Ok(Some(Aes256Gcm::new(&key)))
+}
+
+fn decode_key(encoded: &str) -> Result<Key<Aes256Gcm>, String> {
+    let bytes = STANDARD
+        .decode(encoded.trim())
+        .map_err(|e| format!("Invalid history key: {}", e))?;
+
+    if bytes.len() != 32 {
+        return Err("Invalid history key: expected 256 bits".to_string());
+    }
+
+    Ok(*Key::<Aes256Gcm>::from_slice(&bytes))
+}
+
+fn keyring_entry() -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)
+        .map_err(|e| format!("Failed to access OS keyring: {}", e))
+}
+
+fn read_keyring_key() -> Result<Option<String>, String> {
+    match keyring_entry()?.get_password() {
+        Ok(key) => Ok(Some(key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to read history key from OS keyring: {}", e)),
+    }
+}
+
+fn write_keyring_key(encoded: &str) -> Result<(), String> {
+    keyring_entry()?
+        .set_password(encoded)
+        .map_err(|e| format!("Failed to store history key in OS keyring: {}", e))
+}
+
+fn read_key_file() -> Result<Option<String>, String> {
+    let key_path = get_key_file_path();
+
+    if !key_path.exists() {
+        return Ok(None);
+    }
+
+    fs::read_to_string(&key_path)
+        .map(Some)
+        .map_err(|e| format!("Failed to read history key file: {}", e))
+}
+
+fn write_key_file(encoded: &str) -> Result<(), String> {
+    write_key(&get_key_file_path(), encoded)
+}
+
+fn write_key(key_path: &Path, encoded: &str) -> Result<(), String> {
+    if let Some(parent) = key_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    // Created readable by the owner only, so the key is never briefly exposed
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(key_path).map_err(|e| e.to_string())?;
+    file.write_all(encoded.as_bytes())
+        .map_err(|e| e.to_string())
+}
+
+/// Encrypts a log line as base64 of the nonce followed by the ciphertext
+pub(crate) fn encrypt(cipher: &Aes256Gcm, plaintext: &str) -> Result<String, String> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|e| format!("Failed to encrypt history record: {}", e))?;
+
+    let mut bytes = nonce.to_vec();
+    bytes.extend_from_slice(&ciphertext);
+    Ok(STANDARD.encode(bytes))
+}
+
+pub(crate) fn decrypt(cipher: &Aes256Gcm, line: &str) -> Result<String, String> {
+    let bytes = STANDARD
+        .decode(line.trim())
+        .map_err(|e| format!("Invalid encrypted history record: {}", e))?;
+
+    if bytes.len() <= NONCE_LEN {
+        return Err("Invalid encrypted history record: too short".to_string());
+    }
+
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Failed to decrypt history record".to_string())?;
+
+    String::from_utf8(plaintext).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher() -> Aes256Gcm {
+        Aes256Gcm::new(&Aes256Gcm::generate_key(OsRng))
+    }
+
+    #[test]
+    fn decrypts_what_it_encrypted() {
+        let cipher = cipher();
+
+        let first = encrypt(&cipher, r#"{"query":"rust"}"#).unwrap();
+        let second = encrypt(&cipher, r#"{"query":"rust"}"#).unwrap();
+
+        // A fresh nonce every time, so equal records don't show up as equal lines
+        assert_ne!(first, second);
+        assert_eq!(decrypt(&cipher, &first).unwrap(), r#"{"query":"rust"}"#);
+        assert_eq!(decrypt(&cipher, &second).unwrap(), r#"{"query":"rust"}"#);
+    }
+
+    #[test]
+    fn refuses_lines_it_cannot_decrypt() {
+        let line = encrypt(&cipher(), "secret").unwrap();
+
+        assert_eq!(
+            decrypt(&cipher(), &line),
+            Err("Failed to decrypt history record".to_string())
+        );
+        assert!(decrypt(&cipher(), "not base64!").is_err());
+        assert!(decrypt(&cipher(), &STANDARD.encode([0; NONCE_LEN])).is_err());
+    }
+
+    #[test]
+    fn decodes_only_256_bit_keys() {
+        let key = Aes256Gcm::generate_key(OsRng);
+
+        assert_eq!(decode_key(&format!("{}\n", STANDARD.encode(key))), Ok(key));
+        assert!(decode_key(&STANDARD.encode([0; 16])).is_err());
+        assert!(decode_key("not base64!").is_err());
+    }
+
+    #[test]
+    fn writes_the_key_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let key_path = dir.path().join("zephyr").join("history.key");
+
+        write_key(&key_path, "key").unwrap();
+
+        assert_eq!(fs::read_to_string(&key_path).unwrap(), "key");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&key_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
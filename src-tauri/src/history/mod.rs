@@ -0,0 +1,11 @@
+mod api;
+mod crypto;
+mod models;
+mod storage;
+
+pub use api::{
+    clear_history, delete_entry, load_history, query_history, record_search, reload_config,
+    strip_incognito_prefix, update_config,
+};
+pub use models::{HistoryConfig, HistoryEntry, SearchHistory};
+pub use storage::read_config;
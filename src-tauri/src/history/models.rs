@@ -0,0 +1,79 @@
+use aes_gcm::Aes256Gcm;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Number of visit timestamps kept per entry for frecency scoring
+pub(crate) const MAX_RECENT_VISITS: usize = 10;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryEntry {
+    pub query: String,
+    pub url: String,
+    pub visit_count: u32,
+    pub last_visited: DateTime<Utc>,
+    pub frecency: f64,
+    #[serde(skip)]
+    pub recent_visits: Vec<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryEncryption {
+    #[default]
+    None,
+    /// Key stored in the OS keyring (Windows Credential Manager, macOS Keychain, ...)
+    Keyring,
+    /// Key stored in a file next to the history log
+    KeyFile,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// Queries starting with this prefix are searched without being recorded
+    pub incognito_prefix: String,
+    /// Bangs that are never recorded, without the `!`
+    pub excluded_bangs: Vec<String>,
+    /// Regexes matched against the query; matching queries are never recorded
+    pub excluded_patterns: Vec<String>,
+    pub max_age_days: Option<u32>,
+    pub max_entries: Option<usize>,
+    pub encryption: HistoryEncryption,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            incognito_prefix: "*".to_string(),
+            excluded_bangs: Vec::new(),
+            excluded_patterns: Vec::new(),
+            max_age_days: None,
+            max_entries: None,
+            encryption: HistoryEncryption::None,
+        }
+    }
+}
+
+/// All history entries, keyed by query, along with the privacy settings applied to them
+#[derive(Default)]
+pub struct SearchHistory {
+    pub entries: HashMap<String, HistoryEntry>,
+    pub config: HistoryConfig,
+    /// When set, nothing is recorded until it's turned off again
+    pub incognito: bool,
+    pub(crate) exclusions: Vec<Regex>,
+    pub(crate) cipher: Option<Aes256Gcm>,
+    /// Records in the log that couldn't be decrypted when it was loaded
+    pub(crate) undecryptable: usize,
+}
+
+/// A single line of the append-only history log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct HistoryRecord {
+    pub query: String,
+    pub url: String,
+    /// Unix timestamp in seconds
+    pub timestamp: i64,
+}
@@ -0,0 +1,234 @@
+use crate::history::crypto::{decrypt, encrypt};
+use crate::history::models::{HistoryConfig, HistoryRecord};
+use crate::logger;
+use crate::persist::{self, Schema};
+use aes_gcm::Aes256Gcm;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Version 1 added the versioned envelope
+const CONFIG_SCHEMA: Schema = Schema::new(&[persist::unchanged]);
+
+pub(crate) fn get_history_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("zephyr")
+        .join("history.jsonl")
+}
+
+pub(crate) fn get_config_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("zephyr")
+        .join("history_config.json")
+}
+
+pub(crate) fn load_config() -> HistoryConfig {
+    match persist::read(&get_config_path(), &CONFIG_SCHEMA) {
+        Ok(config) => config.unwrap_or_default(),
+        Err(e) => {
+            logger::error(&format!("Failed to load history config: {}", e));
+            HistoryConfig::default()
+        }
+    }
+}
+
+/// Reads the config after it was edited by hand, reporting errors instead of recovering a backup
+pub fn read_config() -> Result<Option<HistoryConfig>, String> {
+    persist::read_without_recovery(&get_config_path(), &CONFIG_SCHEMA)
+}
+
+pub(crate) fn save_config(config: &HistoryConfig) -> Result<(), String> {
+    persist::write(&get_config_path(), &CONFIG_SCHEMA, config)
+}
+
+/// Records read from the log, and whether the log should be rewritten
+#[derive(Default)]
+pub(crate) struct LoadedRecords {
+    pub records: Vec<HistoryRecord>,
+    /// Some lines are stored differently from the current encryption setting
+    pub format_changed: bool,
+    /// Some lines couldn't be decrypted, so rewriting the log would lose them
+    pub undecryptable: usize,
+}
+
+pub(crate) fn load_records(cipher: Option<&Aes256Gcm>) -> LoadedRecords {
+    let history_path = get_history_path();
+
+    if !history_path.exists() {
+        return LoadedRecords::default();
+    }
+
+    match fs::read_to_string(&history_path) {
+        Ok(content) => parse_records(&content, cipher),
+        Err(e) => {
+            logger::error(&format!("Failed to read search history: {}", e));
+            LoadedRecords::default()
+        }
+    }
+}
+
+fn parse_records(content: &str, cipher: Option<&Aes256Gcm>) -> LoadedRecords {
+    let mut loaded = LoadedRecords::default();
+    let mut invalid_count = 0;
+
+    for line in content.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let is_plaintext = line.starts_with('{');
+        loaded.format_changed |= is_plaintext == cipher.is_some();
+
+        let json = match (is_plaintext, cipher) {
+            (true, _) => line.to_string(),
+            (false, Some(cipher)) => match decrypt(cipher, line) {
+                Ok(json) => json,
+                Err(_) => {
+                    loaded.undecryptable += 1;
+                    continue;
+                }
+            },
+            (false, None) => {
+                loaded.undecryptable += 1;
+                continue;
+            }
+        };
+
+        match serde_json::from_str::<HistoryRecord>(&json) {
+            Ok(record) => loaded.records.push(record),
+            // A partially written last line shouldn't lose the rest of the history
+            Err(_) => invalid_count += 1,
+        }
+    }
+
+    if invalid_count > 0 {
+        logger::warn(&format!(
+            "Skipped {} invalid search history records",
+            invalid_count
+        ));
+    }
+
+    if loaded.undecryptable > 0 {
+        logger::error(&format!(
+            "Could not decrypt {} search history records, check the history encryption key",
+            loaded.undecryptable
+        ));
+    }
+
+    loaded
+}
+
+fn format_record(record: &HistoryRecord, cipher: Option<&Aes256Gcm>) -> Result<String, String> {
+    let json = serde_json::to_string(record).map_err(|e| e.to_string())?;
+
+    match cipher {
+        Some(cipher) => encrypt(cipher, &json),
+        None => Ok(json),
+    }
+}
+
+pub(crate) fn append_record(
+    record: &HistoryRecord,
+    cipher: Option<&Aes256Gcm>,
+) -> Result<(), String> {
+    let history_path = get_history_path();
+
+    if let Some(parent) = history_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    let line = format_record(record, cipher)?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&history_path)
+        .map_err(|e| e.to_string())?;
+
+    writeln!(file, "{}", line).map_err(|e| e.to_string())
+}
+
+/// Replaces the whole log, used when entries are removed or the encryption setting changes
+pub(crate) fn rewrite_records(
+    records: &[HistoryRecord],
+    cipher: Option<&Aes256Gcm>,
+) -> Result<(), String> {
+    let mut content = String::new();
+    for record in records {
+        content.push_str(&format_record(record, cipher)?);
+        content.push('\n');
+    }
+
+    // No backups, removed entries shouldn't stay around in them
+    persist::write_file(&get_history_path(), content.as_bytes(), 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes_gcm::aead::{KeyInit, OsRng};
+
+    fn cipher() -> Aes256Gcm {
+        Aes256Gcm::new(&Aes256Gcm::generate_key(OsRng))
+    }
+
+    fn record(query: &str) -> HistoryRecord {
+        HistoryRecord {
+            query: query.to_string(),
+            url: format!("https://example.com/?q={}", query),
+            timestamp: 1_700_000_000,
+        }
+    }
+
+    fn log(lines: &[String]) -> String {
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+
+    #[test]
+    fn reads_back_encrypted_records() {
+        let cipher = cipher();
+        let content = log(&[
+            format_record(&record("rust"), Some(&cipher)).unwrap(),
+            format_record(&record("go"), Some(&cipher)).unwrap(),
+        ]);
+
+        let loaded = parse_records(&content, Some(&cipher));
+
+        let queries: Vec<&str> = loaded.records.iter().map(|r| r.query.as_str()).collect();
+        assert_eq!(queries, ["rust", "go"]);
+        assert!(!loaded.format_changed);
+        assert_eq!(loaded.undecryptable, 0);
+    }
+
+    #[test]
+    fn counts_lines_it_cannot_decrypt() {
+        let content = log(&[
+            format_record(&record("rust"), Some(&cipher())).unwrap(),
+            format_record(&record("go"), None).unwrap(),
+            "not base64!".to_string(),
+        ]);
+
+        // With another key, only the plaintext line can be read
+        let loaded = parse_records(&content, Some(&cipher()));
+        assert_eq!(loaded.records.len(), 1);
+        assert_eq!(loaded.undecryptable, 2);
+        assert!(loaded.format_changed);
+
+        // Without a key, encrypted lines can't be read either
+        let loaded = parse_records(&content, None);
+        assert_eq!(loaded.records.len(), 1);
+        assert_eq!(loaded.undecryptable, 2);
+        assert!(loaded.format_changed);
+    }
+
+    #[test]
+    fn skips_a_partially_written_line() {
+        let content = format!(
+            "{}{{\"query\":\"ru",
+            log(&[format_record(&record("go"), None).unwrap()])
+        );
+
+        let loaded = parse_records(&content, None);
+
+        assert_eq!(loaded.records.len(), 1);
+        assert_eq!(loaded.undecryptable, 0);
+    }
+}
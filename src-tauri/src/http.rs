@@ -0,0 +1,556 @@
+use crate::logger;
+use crate::persist::{self, Schema};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+const KEYRING_SERVICE: &str = "zephyr";
+const KEYRING_USER: &str = "proxy-password";
+
+/// Version 1 added the versioned envelope
+const CONFIG_SCHEMA: Schema = Schema::new(&[persist::unchanged]);
+
+/// Endpoints checked by the diagnostics when no URL is given
+const DIAGNOSTIC_URLS: &[&str] = &[
+    "https://duckduckgo.com/bang.js",
+    "https://suggestqueries.google.com/complete/search?client=chrome&q=zephyr",
+    "https://github.com/zmconnelly/zephyr/releases/latest/download/latest.json",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProxySettings {
+    /// Proxy for every request, e.g. `http://proxy.example.com:8080`
+    pub url: String,
+    /// The password is kept in the OS keyring rather than in the settings file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    /// Hosts reached without the proxy, e.g. `localhost`, `.example.com` or `10.0.0.0/8`
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkSettings {
+    /// Without an explicit proxy, the `HTTP_PROXY`/`HTTPS_PROXY` environment variables apply
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<ProxySettings>,
+    /// PEM files with root certificates to trust on top of the built-in ones
+    #[serde(default)]
+    pub ca_bundles: Vec<PathBuf>,
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_user_agent")]
+    pub user_agent: String,
+}
+
+fn default_connect_timeout_secs() -> u64 {
+    10
+}
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+fn default_user_agent() -> String {
+    // Some APIs, like crates.io, reject requests without a user agent
+    format!("Zephyr/{}", env!("CARGO_PKG_VERSION"))
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            ca_bundles: Vec::new(),
+            connect_timeout_secs: default_connect_timeout_secs(),
+            timeout_secs: default_timeout_secs(),
+            user_agent: default_user_agent(),
+        }
+    }
+}
+
+impl NetworkSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(proxy) = &self.proxy {
+            let url = url::Url::parse(&proxy.url)
+                .map_err(|e| format!("Invalid proxy URL '{}': {}", proxy.url, e))?;
+            if !matches!(url.scheme(), "http" | "https") {
+                return Err(format!("Unsupported proxy scheme: '{}'", url.scheme()));
+            }
+        }
+
+        for path in &self.ca_bundles {
+            if !path.is_file() {
+                return Err(format!("CA bundle not found: {}", path.display()));
+            }
+        }
+
+        if self.connect_timeout_secs == 0 || self.timeout_secs == 0 {
+            return Err("Timeouts must be at least one second".to_string());
+        }
+
+        if self.user_agent.trim().is_empty() {
+            return Err("User agent can't be empty".to_string());
+        }
+
+        Ok(())
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
+
+lazy_static! {
+    static ref SETTINGS: RwLock<NetworkSettings> = RwLock::new(load_network_settings());
+    // Shared so connections are reused across requests; rebuilt when the settings change
+    static ref CLIENT: RwLock<reqwest::Client> = RwLock::new(build_initial_client());
+    // Why the settings couldn't be fully applied, shown with the network settings
+    static ref SETTINGS_ERROR: RwLock<Option<String>> = RwLock::new(None);
+}
+
+/// What the network settings add to an HTTP client, prepared so it can be applied to
+/// clients built elsewhere too, like the updater's
+#[derive(Clone)]
+pub struct ClientOptions {
+    settings: NetworkSettings,
+    proxy: Option<reqwest::Proxy>,
+    certificates: Vec<reqwest::Certificate>,
+}
+
+impl ClientOptions {
+    /// Prepares the settings, leaving out what can't be applied along with why.
+    /// A proxy password that can't be read leaves the proxy without credentials,
+    /// rather than connecting without the proxy.
+    fn prepare(settings: &NetworkSettings) -> (Self, Vec<String>) {
+        let mut errors = Vec::new();
+
+        let proxy = settings.proxy.as_ref().and_then(|proxy_settings| {
+            let mut proxy = reqwest::Proxy::all(&proxy_settings.url)
+                .map_err(|e| {
+                    errors.push(format!("Invalid proxy URL '{}': {}", proxy_settings.url, e))
+                })
+                .ok()?;
+
+            if let Some(username) = &proxy_settings.username {
+                let password = read_proxy_password().unwrap_or_else(|e| {
+                    errors.push(e);
+                    None
+                });
+                proxy = proxy.basic_auth(username, &password.unwrap_or_default());
+            }
+
+            Some(proxy.no_proxy(reqwest::NoProxy::from_string(
+                &proxy_settings.no_proxy.join(","),
+            )))
+        });
+
+        let mut certificates = Vec::new();
+        for path in &settings.ca_bundles {
+            let pem = match fs::read(path) {
+                Ok(pem) => pem,
+                Err(e) => {
+                    errors.push(format!(
+                        "Failed to read CA bundle {}: {}",
+                        path.display(),
+                        e
+                    ));
+                    continue;
+                }
+            };
+            match reqwest::Certificate::from_pem_bundle(&pem) {
+                Ok(bundle) => certificates.extend(bundle),
+                Err(e) => errors.push(format!("Invalid CA bundle {}: {}", path.display(), e)),
+            }
+        }
+
+        let options = Self {
+            settings: settings.clone(),
+            proxy,
+            certificates,
+        };
+        (options, errors)
+    }
+
+    /// Prepares the settings, failing if any of them can't be applied
+    fn prepare_all(settings: &NetworkSettings) -> Result<Self, String> {
+        match Self::prepare(settings) {
+            (options, errors) if errors.is_empty() => Ok(options),
+            (_, errors) => Err(errors.join("; ")),
+        }
+    }
+
+    fn apply(&self, builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        self.apply_connection(builder)
+            .user_agent(&self.settings.user_agent)
+            .timeout(self.settings.timeout())
+            .pool_idle_timeout(Duration::from_secs(90))
+    }
+
+    /// Applies only how to connect: the proxy, certificates and connect timeout. For
+    /// clients like the updater's, whose downloads can take longer than any request timeout.
+    pub fn apply_connection(&self, builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        let mut builder =
+            builder.connect_timeout(Duration::from_secs(self.settings.connect_timeout_secs));
+
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(proxy.clone());
+        }
+
+        for certificate in &self.certificates {
+            builder = builder.add_root_certificate(certificate.clone());
+        }
+
+        builder
+    }
+
+    fn build(&self) -> Result<reqwest::Client, String> {
+        self.apply(reqwest::Client::builder())
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))
+    }
+}
+
+/// Applies as much of the saved settings as possible, so a keyring or CA bundle that
+/// can't be read doesn't stop requests from going through the proxy
+fn build_initial_client() -> reqwest::Client {
+    let (options, errors) = ClientOptions::prepare(&SETTINGS.read().unwrap());
+    if !errors.is_empty() {
+        let error = errors.join("; ");
+        logger::error(&format!("Failed to apply network settings: {}", error));
+        *SETTINGS_ERROR.write().unwrap() = Some(error);
+    }
+
+    options.build().unwrap_or_else(|e| {
+        logger::error(&format!("{}, using the default network settings", e));
+        *SETTINGS_ERROR.write().unwrap() = Some(e);
+        reqwest::Client::new()
+    })
+}
+
+fn build_client(settings: &NetworkSettings) -> Result<reqwest::Client, String> {
+    ClientOptions::prepare_all(settings)?.build()
+}
+
+/// The client every outbound request goes through
+pub fn client() -> reqwest::Client {
+    CLIENT.read().unwrap().clone()
+}
+
+pub fn settings() -> NetworkSettings {
+    SETTINGS.read().unwrap().clone()
+}
+
+/// The current settings prepared for clients that aren't the shared one
+pub fn client_options() -> Result<ClientOptions, String> {
+    ClientOptions::prepare_all(&settings())
+}
+
+/// Validates, saves and applies the settings to every following request
+pub fn update_settings(settings: NetworkSettings) -> Result<(), String> {
+    settings.validate()?;
+    let client = build_client(&settings)?;
+    save_network_settings(&settings)?;
+
+    install_settings(settings, client);
+    Ok(())
+}
+
+/// Applies settings read from the config file without saving them again
+pub fn reload_settings(settings: NetworkSettings) -> Result<(), String> {
+    settings.validate()?;
+    let client = build_client(&settings)?;
+
+    install_settings(settings, client);
+    Ok(())
+}
+
+fn install_settings(settings: NetworkSettings, client: reqwest::Client) {
+    *CLIENT.write().unwrap() = client;
+    *SETTINGS.write().unwrap() = settings;
+    *SETTINGS_ERROR.write().unwrap() = None;
+}
+
+fn get_config_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("zephyr")
+        .join("network.json")
+}
+
+fn load_network_settings() -> NetworkSettings {
+    match persist::read(&get_config_path(), &CONFIG_SCHEMA) {
+        Ok(settings) => settings.unwrap_or_default(),
+        Err(e) => {
+            logger::error(&format!("Failed to load network settings: {}", e));
+            NetworkSettings::default()
+        }
+    }
+}
+
+/// Reads the settings after they were edited by hand, reporting errors instead of recovering a backup
+pub fn read_network_settings() -> Result<Option<NetworkSettings>, String> {
+    persist::read_without_recovery(&get_config_path(), &CONFIG_SCHEMA)
+}
+
+fn save_network_settings(settings: &NetworkSettings) -> Result<(), String> {
+    persist::write(&get_config_path(), &CONFIG_SCHEMA, settings)
+}
+
+fn keyring_entry() -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)
+        .map_err(|e| format!("Failed to access OS keyring: {}", e))
+}
+
+fn read_proxy_password() -> Result<Option<String>, String> {
+    match keyring_entry()?.get_password() {
+        Ok(password) => Ok(Some(password)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!(
+            "Failed to read proxy password from OS keyring: {}",
+            e
+        )),
+    }
+}
+
+fn write_proxy_password(password: Option<&str>) -> Result<(), String> {
+    let entry = keyring_entry()?;
+    let result = match password {
+        Some(password) => entry.set_password(password),
+        None => match entry.delete_credential() {
+            Err(keyring::Error::NoEntry) => Ok(()),
+            result => result,
+        },
+    };
+
+    result.map_err(|e| format!("Failed to store proxy password in OS keyring: {}", e))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionCheck {
+    pub url: String,
+    pub status: Option<u16>,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+async fn check_connection(client: &reqwest::Client, url: &str) -> ConnectionCheck {
+    let started = Instant::now();
+    let result = client.head(url).send().await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    let (status, error) = match result {
+        Ok(response) => (Some(response.status().as_u16()), None),
+        // Include the causes, the top-level error rarely says whether the proxy or TLS failed
+        Err(e) => {
+            let mut message = e.to_string();
+            let mut source = std::error::Error::source(&e);
+            while let Some(cause) = source {
+                message.push_str(&format!(": {}", cause));
+                source = cause.source();
+            }
+            (None, Some(message))
+        }
+    };
+
+    ConnectionCheck {
+        url: url.to_string(),
+        status,
+        latency_ms,
+        error,
+    }
+}
+
+/// The network settings along with why they couldn't be fully applied, if they couldn't
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkSettingsStatus {
+    #[serde(flatten)]
+    pub settings: NetworkSettings,
+    pub error: Option<String>,
+}
+
+pub fn settings_status() -> NetworkSettingsStatus {
+    // The client is built first, that's when the error is found
+    let _ = client();
+
+    NetworkSettingsStatus {
+        settings: settings(),
+        error: SETTINGS_ERROR.read().unwrap().clone(),
+    }
+}
+
+/// Stores the proxy password in the OS keyring, or removes it when `None`
+#[tauri::command]
+pub fn set_proxy_password(password: Option<String>) -> Result<(), String> {
+    write_proxy_password(password.as_deref())?;
+
+    // The password is baked into the client, so it has to be rebuilt
+    let client = build_client(&settings())?;
+    *CLIENT.write().unwrap() = client;
+    *SETTINGS_ERROR.write().unwrap() = None;
+    Ok(())
+}
+
+/// Requests each URL through the shared client and reports how it went
+#[tauri::command]
+pub async fn test_network_connection(url: Option<String>) -> Vec<ConnectionCheck> {
+    let urls = match url {
+        Some(url) => vec![url],
+        None => DIAGNOSTIC_URLS.iter().map(|url| url.to_string()).collect(),
+    };
+
+    let client = client();
+    let mut checks = Vec::new();
+    for url in urls {
+        let check = check_connection(&client, &url).await;
+        match &check.error {
+            Some(e) => logger::warn(&format!(
+                "Connection check failed for {}: {}",
+                logger::redact_url(&url),
+                e
+            )),
+            None => logger::info(&format!(
+                "Connection check for {}: {:?} in {}ms",
+                logger::redact_url(&url),
+                check.status,
+                check.latency_ms
+            )),
+        }
+        checks.push(check);
+    }
+
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{self, Route};
+    use std::path::Path;
+
+    const ISRG_ROOT_X2: &str = "-----BEGIN CERTIFICATE-----
+MIICGzCCAaGgAwIBAgIQQdKd0XLq7qeAwSxs6S+HUjAKBggqhkjOPQQDAzBPMQsw
+CQYDVQQGEwJVUzEpMCcGA1UEChMgSW50ZXJuZXQgU2VjdXJpdHkgUmVzZWFyY2gg
+R3JvdXAxFTATBgNVBAMTDElTUkcgUm9vdCBYMjAeFw0yMDA5MDQwMDAwMDBaFw00
+MDA5MTcxNjAwMDBaME8xCzAJBgNVBAYTAlVTMSkwJwYDVQQKEyBJbnRlcm5ldCBT
+ZWN1cml0eSBSZXNlYXJjaCBHcm91cDEVMBMGA1UEAxMMSVNSRyBSb290IFgyMHYw
+EAYHKoZIzj0CAQYFK4EEACIDYgAEzZvVn4CDCuwJSvMWSj5cz3es3mcFDR0HttwW
++1qLFNvicWDEukWVEYmO6gbf9yoWHKS5xcUy4APgHoIYOIvXRdgKam7mAHf7AlF9
+ItgKbppbd9/w+kHsOdx1ymgHDB/qo0IwQDAOBgNVHQ8BAf8EBAMCAQYwDwYDVR0T
+AQH/BAUwAwEB/zAdBgNVHQ4EFgQUfEKWrt5LSDv6kviejM9ti6lyN5UwCgYIKoZI
+zj0EAwMDaAAwZQIwe3lORlCEwkSHRhtFcP9Ymd70/aTSVaYgLXTWNLxBo1BfASdW
+tL4ndQavEi51mI38AjEAi/V3bNTIZargCyzuFJ0nN6T5U6VR5CmD1/iQMVtCnwr1
+/q4AaOeMSQ+2b1tbFfLn
+-----END CERTIFICATE-----
+";
+
+    fn proxy(url: &str, no_proxy: &[&str]) -> ProxySettings {
+        ProxySettings {
+            url: url.to_string(),
+            username: None,
+            no_proxy: no_proxy.iter().map(|host| host.to_string()).collect(),
+        }
+    }
+
+    fn write_bundle(dir: &Path, name: &str, content: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn validates_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = write_bundle(dir.path(), "ca.pem", ISRG_ROOT_X2);
+        let valid = NetworkSettings {
+            proxy: Some(proxy("http://proxy.example.com:8080", &[])),
+            ca_bundles: vec![bundle],
+            ..Default::default()
+        };
+        assert_eq!(valid.validate(), Ok(()));
+
+        let cases = [
+            NetworkSettings {
+                proxy: Some(proxy("proxy.example.com", &[])),
+                ..Default::default()
+            },
+            NetworkSettings {
+                proxy: Some(proxy("socks5://proxy.example.com:1080", &[])),
+                ..Default::default()
+            },
+            NetworkSettings {
+                ca_bundles: vec![dir.path().join("missing.pem")],
+                ..Default::default()
+            },
+            NetworkSettings {
+                connect_timeout_secs: 0,
+                ..Default::default()
+            },
+            NetworkSettings {
+                timeout_secs: 0,
+                ..Default::default()
+            },
+            NetworkSettings {
+                user_agent: " ".to_string(),
+                ..Default::default()
+            },
+        ];
+        for settings in cases {
+            assert!(settings.validate().is_err(), "{:?}", settings);
+        }
+    }
+
+    #[test]
+    fn loads_ca_bundles() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = write_bundle(
+            dir.path(),
+            "bundle.pem",
+            &format!("{}{}", ISRG_ROOT_X2, ISRG_ROOT_X2),
+        );
+        let broken = write_bundle(
+            dir.path(),
+            "broken.pem",
+            "-----BEGIN CERTIFICATE-----\nnot a certificate\n-----END CERTIFICATE-----\n",
+        );
+        let settings = NetworkSettings {
+            ca_bundles: vec![bundle, broken.clone(), dir.path().join("missing.pem")],
+            ..Default::default()
+        };
+
+        let (options, errors) = ClientOptions::prepare(&settings);
+
+        // The readable bundle is still applied
+        assert_eq!(options.certificates.len(), 2);
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with(&format!("Invalid CA bundle {}", broken.display())));
+        assert!(errors[1].starts_with("Failed to read CA bundle"));
+        assert!(ClientOptions::prepare_all(&settings).is_err());
+    }
+
+    #[tokio::test]
+    async fn bypasses_the_proxy_for_no_proxy_hosts() {
+        let base_url = test_server::serve(vec![Route::new("/", "text/plain", "direct")]);
+        // Nothing listens on the discard port, so anything sent through the proxy fails
+        let client_with = |no_proxy: &[&str]| {
+            let settings = NetworkSettings {
+                proxy: Some(proxy("http://127.0.0.1:9", no_proxy)),
+                ..Default::default()
+            };
+            build_client(&settings).unwrap()
+        };
+
+        let bypassed = client_with(&["localhost", " 127.0.0.1 "])
+            .get(&base_url)
+            .send()
+            .await;
+        assert_eq!(bypassed.unwrap().text().await.unwrap(), "direct");
+
+        let proxied = client_with(&["example.com"]).get(&base_url).send().await;
+        assert!(proxied.is_err());
+    }
+}
@@ -1,18 +1,31 @@
 #![allow(deprecated)]
 
+use std::collections::{HashMap, HashSet};
 use std::env;
-use std::sync::Mutex;
-use std::{collections::HashMap, sync::Arc};
+use std::sync::{Arc, Mutex};
 use system_tray::initialize_tray;
-use tauri::Manager;
+use tauri::{AppHandle, Manager};
+use tokio::sync::watch;
 
 use windows_key_listener::KeyListener;
 
 mod bangs;
-mod logger;
+mod config_watcher;
+mod connectivity;
+mod detectors;
+mod history;
+mod http;
+mod locale;
+pub mod logger;
+mod persist;
+mod rules;
 mod search;
+mod settings;
 mod startup;
+mod suggestions;
 mod system_tray;
+#[cfg(test)]
+mod test_server;
 mod updater;
 
 pub fn run() {
@@ -26,27 +39,17 @@ pub fn run() {
                 app.package_info().version.to_string()
             ));
 
-            let key_listener = KeyListener::new();
+            persist::init(app.handle());
+            connectivity::start_monitor(app.handle().clone());
 
-            let app_handle = app.handle().clone();
-
-            key_listener
-                .listen(
-                    "Ctrl + Space",
-                    std::time::Duration::from_millis(200),
-                    Arc::new(move || {
-                        if let Some(window) = app_handle.get_webview_window("main") {
-                            if !window.is_visible().unwrap_or(false) {
-                                let _ = window.show();
-                                let _ = window.set_focus();
-                                return true;
-                            }
-                        }
+            let settings = settings::load_settings();
+            let settings_state = settings::SettingsState::new(settings.clone());
+            startup::follow_settings(settings_state.subscribe());
+            follow_hotkey_setting(app.handle(), settings_state.subscribe());
+            follow_bang_cache_ttl_setting(app.handle(), settings_state.subscribe());
+            app.manage(settings_state);
 
-                        return false;
-                    }),
-                )
-                .expect("Failed to listen to key chord");
+            register_hotkey(app.handle(), &settings.hotkey).expect("Failed to listen to key chord");
 
             let app_handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
@@ -57,6 +60,7 @@ pub fn run() {
 
                 // Wait a bit before checking for updates
                 std::thread::sleep(std::time::Duration::from_secs(5));
+                connectivity::wait_until_online().await;
 
                 if let Err(e) = updater::check_for_updates(app_handle).await {
                     logger::error(&format!("Update check failed: {}", e));
@@ -70,10 +74,11 @@ pub fn run() {
 
             // Create initial bang state with fallback bangs
             let initial_bangs = HashMap::new();
+            let bang_cache_ttl = settings.bang_cache_ttl();
 
             // Spawn a task to load bangs asynchronously
             tauri::async_runtime::spawn(async move {
-                let loaded_bangs = bangs::load_all_bangs(&app_handle).await;
+                let loaded_bangs = bangs::load_all_bangs(&app_handle, bang_cache_ttl).await;
 
                 // Update the state with loaded bangs
                 if let Some(state) = app_handle.try_state::<search::BangState>() {
@@ -85,8 +90,55 @@ pub fn run() {
             // Register the bang state
             app.manage(search::BangState {
                 bangs: Mutex::new(initial_bangs),
+                keywords: Mutex::new(bangs::load_keywords(app.handle())),
             });
 
+            let detector_config = detectors::load_detector_config();
+            app.manage(search::DetectorState {
+                pipeline: Mutex::new(detectors::DetectorPipeline::from_config(&detector_config)),
+                config: Mutex::new(detector_config),
+            });
+
+            app.manage(search::RuleState {
+                rules: Mutex::new(rules::load_rules()),
+            });
+
+            app.manage(search::HistoryState {
+                history: Mutex::new(history::load_history()),
+            });
+
+            let locale_settings = locale::load_locale_settings();
+            logger::info(&format!("Using locale: {}", locale_settings.tag()));
+
+            let suggestion_config = suggestions::load_suggestion_config();
+            let providers = suggestion_config.build_providers();
+
+            // Connect to the suggestion providers before the search box is first opened
+            for provider in providers.clone() {
+                let locale_settings = locale_settings.clone();
+                tauri::async_runtime::spawn(async move {
+                    suggestions::warm_up(provider.as_ref(), &locale_settings).await
+                });
+            }
+
+            app.manage(search::LocaleState {
+                settings: Mutex::new(locale_settings),
+            });
+
+            app.manage(search::SuggestionState {
+                providers: Mutex::new(providers),
+                config: Mutex::new(suggestion_config),
+                cache: Arc::new(suggestions::SuggestionCache::new()),
+                stats: Arc::new(suggestions::ProviderStats::default()),
+                latest_seq: tokio::sync::watch::channel(0).0,
+            });
+
+            // Apply config files edited by hand without restarting
+            app.manage(config_watcher::ConfigWatchState::default());
+            if let Err(e) = config_watcher::start(app.handle()) {
+                logger::error(&e);
+            }
+
             // Hide the window at startup
             if let Some(window) = app.get_webview_window("main") {
                 let _ = window.hide();
@@ -106,17 +158,128 @@ pub fn run() {
         })
         .invoke_handler(tauri::generate_handler![
             search::get_search_suggestions,
+            search::cancel_suggestions,
             search::search,
             search::get_available_bangs,
             search::refresh_bangs,
             search::add_custom_bang,
             search::delete_custom_bang,
+            search::list_bang_revisions,
+            search::diff_bang_revisions,
+            search::restore_bang_revision,
+            search::undo_bang_change,
+            search::export_bangs,
+            search::import_bangs,
+            search::find_browser_profiles,
+            search::import_browser_bangs,
+            search::import_launcher_bangs,
+            search::add_bang_from_opensearch,
+            search::export_browser_policies,
             search::open_url,
             search::clear_bangs_cache,
-            startup::get_startup_status,
-            startup::toggle_run_at_startup,
+            search::get_routing_rule_errors,
+            search::test_routing_rule,
+            search::get_search_history,
+            search::delete_history_entry,
+            search::clear_search_history,
+            search::get_incognito_mode,
+            search::set_incognito_mode,
+            search::get_suggestion_provider_stats,
+            config_watcher::get_config_errors,
+            connectivity::get_connectivity_status,
+            http::set_proxy_password,
+            http::test_network_connection,
+            persist::get_recovered_files,
+            persist::dismiss_recovered_files,
+            settings::api::get_settings,
+            settings::api::update_settings,
+            updater::check_updates,
             logger::log,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+/// Shows the search window when the hotkey is pressed. Key listeners can't be removed, so
+/// listeners for earlier hotkeys stay registered but ignore presses once the setting changes.
+fn register_hotkey(app_handle: &AppHandle, hotkey: &str) -> Result<(), String> {
+    let app_handle = app_handle.clone();
+    let registered_hotkey = hotkey.to_string();
+
+    KeyListener::new()
+        .listen(
+            hotkey,
+            std::time::Duration::from_millis(200),
+            Arc::new(move || {
+                let current_hotkey = app_handle.state::<settings::SettingsState>().get().hotkey;
+                if current_hotkey != registered_hotkey {
+                    return false;
+                }
+
+                if let Some(window) = app_handle.get_webview_window("main") {
+                    if !window.is_visible().unwrap_or(false) {
+                        let _ = window.show();
+                        let _ = window.set_focus();
+                        return true;
+                    }
+                }
+
+                false
+            }),
+        )
+        .map_err(|e| format!("Failed to listen to key chord '{}': {:?}", hotkey, e))
+}
+
+fn follow_hotkey_setting(
+    app_handle: &AppHandle,
+    mut settings: watch::Receiver<settings::Settings>,
+) {
+    let app_handle = app_handle.clone();
+    let mut registered = HashSet::from([settings.borrow().hotkey.clone()]);
+
+    tauri::async_runtime::spawn(async move {
+        while settings.changed().await.is_ok() {
+            let hotkey = settings.borrow_and_update().hotkey.clone();
+            if registered.contains(&hotkey) {
+                continue;
+            }
+
+            logger::info(&format!("Listening for new hotkey: {}", hotkey));
+            match register_hotkey(&app_handle, &hotkey) {
+                Ok(()) => {
+                    registered.insert(hotkey);
+                }
+                Err(e) => logger::error(&e),
+            }
+        }
+    });
+}
+
+/// Loads the bangs again when the cache lifetime is shortened, so a cache that is now too
+/// old gets fetched again without waiting for a restart
+fn follow_bang_cache_ttl_setting(
+    app_handle: &AppHandle,
+    mut settings: watch::Receiver<settings::Settings>,
+) {
+    let app_handle = app_handle.clone();
+    let mut ttl_days = settings.borrow().bang_cache_ttl_days;
+
+    tauri::async_runtime::spawn(async move {
+        while settings.changed().await.is_ok() {
+            let (new_ttl_days, ttl) = {
+                let settings = settings.borrow_and_update();
+                (settings.bang_cache_ttl_days, settings.bang_cache_ttl())
+            };
+            let shortened = new_ttl_days < ttl_days;
+            ttl_days = new_ttl_days;
+            if !shortened {
+                continue;
+            }
+
+            let loaded_bangs = bangs::load_all_bangs(&app_handle, ttl).await;
+            if let Some(state) = app_handle.try_state::<search::BangState>() {
+                *state.bangs.lock().unwrap() = loaded_bangs;
+            }
+        }
+    });
+}
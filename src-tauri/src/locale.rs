@@ -0,0 +1,341 @@
+use crate::logger;
+use crate::persist::{self, Schema};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SafeSearch {
+    Off,
+    /// Leaves filtering to each provider's default
+    #[default]
+    Moderate,
+    Strict,
+}
+
+/// Language, region and safe-search preferences passed on to search and suggestion providers
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LocaleSettings {
+    /// ISO 639 language code, e.g. `en`
+    pub language: String,
+    /// ISO 3166 country code, e.g. `US`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    #[serde(default)]
+    pub safe_search: SafeSearch,
+}
+
+impl Default for LocaleSettings {
+    fn default() -> Self {
+        sys_locale::get_locale()
+            .and_then(|tag| Self::from_tag(&tag))
+            .unwrap_or_else(|| Self {
+                language: "en".to_string(),
+                region: None,
+                safe_search: SafeSearch::default(),
+            })
+    }
+}
+
+impl LocaleSettings {
+    /// Parses a locale tag like `en-US`, `pt_BR.UTF-8` or `zh-Hans-CN`
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let tag = tag.split(['.', '@']).next()?;
+        let mut parts = tag.split(['-', '_']);
+
+        let language = parts.next()?.to_lowercase();
+        if !is_language_code(&language) {
+            return None;
+        }
+
+        // Scripts like `Hans` are skipped, the region is the two-letter part
+        let region = parts
+            .map(|part| part.to_uppercase())
+            .find(|part| is_region_code(part));
+
+        Some(Self {
+            language,
+            region,
+            safe_search: SafeSearch::default(),
+        })
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !is_language_code(&self.language) {
+            return Err(format!("Invalid language code: '{}'", self.language));
+        }
+
+        if let Some(region) = &self.region {
+            if !is_region_code(region) {
+                return Err(format!("Invalid region code: '{}'", region));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Language and region as a tag, e.g. `en-US`
+    pub fn tag(&self) -> String {
+        match &self.region {
+            Some(region) => format!("{}-{}", self.language, region),
+            None => self.language.clone(),
+        }
+    }
+}
+
+fn is_language_code(code: &str) -> bool {
+    (2..=3).contains(&code.len()) && code.chars().all(|c| c.is_ascii_lowercase())
+}
+
+fn is_region_code(code: &str) -> bool {
+    code.len() == 2 && code.chars().all(|c| c.is_ascii_uppercase())
+}
+
+/// Version 1 added the versioned envelope
+const CONFIG_SCHEMA: Schema = Schema::new(&[persist::unchanged]);
+
+fn get_config_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("zephyr")
+        .join("locale.json")
+}
+
+/// Loads the saved locale settings, falling back to the OS locale
+pub fn load_locale_settings() -> LocaleSettings {
+    let settings =
+        persist::read::<LocaleSettings>(&get_config_path(), &CONFIG_SCHEMA).and_then(|settings| {
+            match settings {
+                Some(settings) => settings.validate().map(|_| settings),
+                None => Ok(LocaleSettings::default()),
+            }
+        });
+
+    match settings {
+        Ok(settings) => settings,
+        Err(e) => {
+            logger::error(&format!("Failed to load locale settings: {}", e));
+            LocaleSettings::default()
+        }
+    }
+}
+
+/// Reads the settings after they were edited by hand, reporting errors instead of recovering a backup
+pub fn read_locale_settings() -> Result<Option<LocaleSettings>, String> {
+    persist::read_without_recovery(&get_config_path(), &CONFIG_SCHEMA)
+}
+
+pub fn save_locale_settings(settings: &LocaleSettings) -> Result<(), String> {
+    persist::write(&get_config_path(), &CONFIG_SCHEMA, settings)
+}
+
+/// Query parameters Google uses for the locale, for both search and suggestions
+pub fn google_params(settings: &LocaleSettings) -> Vec<(&'static str, String)> {
+    let mut params = vec![("hl", settings.language.clone())];
+    if let Some(region) = &settings.region {
+        params.push(("gl", region.clone()));
+    }
+    match settings.safe_search {
+        SafeSearch::Strict => params.push(("safe", "active".to_string())),
+        SafeSearch::Off => params.push(("safe", "off".to_string())),
+        SafeSearch::Moderate => {}
+    }
+    params
+}
+
+pub fn duckduckgo_params(settings: &LocaleSettings) -> Vec<(&'static str, String)> {
+    let mut params = Vec::new();
+    // DuckDuckGo regions are written country first, e.g. `us-en`
+    if let Some(region) = &settings.region {
+        params.push((
+            "kl",
+            format!("{}-{}", region.to_lowercase(), settings.language),
+        ));
+    }
+    match settings.safe_search {
+        SafeSearch::Strict => params.push(("kp", "1".to_string())),
+        SafeSearch::Off => params.push(("kp", "-2".to_string())),
+        SafeSearch::Moderate => {}
+    }
+    params
+}
+
+pub fn bing_params(settings: &LocaleSettings) -> Vec<(&'static str, String)> {
+    let mut params = vec![("setlang", settings.language.clone())];
+    // The market picks the suggestions, the country code the search results
+    if let Some(region) = &settings.region {
+        params.push(("mkt", settings.tag()));
+        params.push(("cc", region.clone()));
+    }
+    match settings.safe_search {
+        SafeSearch::Strict => params.push(("adlt", "strict".to_string())),
+        SafeSearch::Off => params.push(("adlt", "off".to_string())),
+        SafeSearch::Moderate => {}
+    }
+    params
+}
+
+pub fn brave_params(settings: &LocaleSettings) -> Vec<(&'static str, String)> {
+    let mut params = Vec::new();
+    if let Some(region) = &settings.region {
+        params.push(("country", region.to_lowercase()));
+    }
+    match settings.safe_search {
+        SafeSearch::Strict => params.push(("safesearch", "strict".to_string())),
+        SafeSearch::Off => params.push(("safesearch", "off".to_string())),
+        SafeSearch::Moderate => {}
+    }
+    params
+}
+
+/// Appends the parameters to the query string of the URL
+pub fn append_params(url: &str, params: &[(&'static str, String)]) -> String {
+    let mut url = url.to_string();
+    for (name, value) in params {
+        let separator = if url.contains('?') { '&' } else { '?' };
+        url.push_str(&format!(
+            "{}{}={}",
+            separator,
+            name,
+            urlencoding::encode(value)
+        ));
+    }
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type EngineParams = fn(&LocaleSettings) -> Vec<(&'static str, String)>;
+
+    fn locale(language: &str, region: Option<&str>, safe_search: SafeSearch) -> LocaleSettings {
+        LocaleSettings {
+            language: language.to_string(),
+            region: region.map(str::to_string),
+            safe_search,
+        }
+    }
+
+    #[test]
+    fn parses_locale_tags() {
+        let cases = [
+            ("en", Some(("en", None))),
+            ("en-US", Some(("en", Some("US")))),
+            ("pt-BR", Some(("pt", Some("BR")))),
+            ("pt_BR.UTF-8", Some(("pt", Some("BR")))),
+            ("zh-Hant-TW", Some(("zh", Some("TW")))),
+            ("de_DE@euro", Some(("de", Some("DE")))),
+            ("EN-us", Some(("en", Some("US")))),
+            ("", None),
+            ("english", None),
+            ("e-US", None),
+            ("12-US", None),
+        ];
+
+        for (tag, expected) in cases {
+            let parsed = LocaleSettings::from_tag(tag);
+            let expected =
+                expected.map(|(language, region)| locale(language, region, SafeSearch::default()));
+            assert_eq!(parsed, expected, "{:?}", tag);
+        }
+    }
+
+    #[test]
+    fn validates_language_and_region() {
+        assert!(locale("en", Some("US"), SafeSearch::Off).validate().is_ok());
+        assert!(locale("fil", None, SafeSearch::Strict).validate().is_ok());
+
+        assert!(locale("EN", None, SafeSearch::default())
+            .validate()
+            .is_err());
+        assert!(locale("english", None, SafeSearch::default())
+            .validate()
+            .is_err());
+        assert!(locale("en", Some("us"), SafeSearch::default())
+            .validate()
+            .is_err());
+        assert!(locale("en", Some("USA"), SafeSearch::default())
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn maps_the_locale_to_each_engine() {
+        let us = locale("en", Some("US"), SafeSearch::Moderate);
+        let brazil_strict = locale("pt", Some("BR"), SafeSearch::Strict);
+        let taiwan_unfiltered = locale("zh", Some("TW"), SafeSearch::Off);
+        let english = locale("en", None, SafeSearch::Moderate);
+
+        let cases: [(EngineParams, &LocaleSettings, &[(&str, &str)]); 12] = [
+            (google_params, &us, &[("hl", "en"), ("gl", "US")]),
+            (
+                google_params,
+                &brazil_strict,
+                &[("hl", "pt"), ("gl", "BR"), ("safe", "active")],
+            ),
+            (google_params, &english, &[("hl", "en")]),
+            (duckduckgo_params, &us, &[("kl", "us-en")]),
+            (
+                duckduckgo_params,
+                &taiwan_unfiltered,
+                &[("kl", "tw-zh"), ("kp", "-2")],
+            ),
+            (duckduckgo_params, &english, &[]),
+            (
+                bing_params,
+                &us,
+                &[("setlang", "en"), ("mkt", "en-US"), ("cc", "US")],
+            ),
+            (
+                bing_params,
+                &brazil_strict,
+                &[
+                    ("setlang", "pt"),
+                    ("mkt", "pt-BR"),
+                    ("cc", "BR"),
+                    ("adlt", "strict"),
+                ],
+            ),
+            (bing_params, &english, &[("setlang", "en")]),
+            (brave_params, &us, &[("country", "us")]),
+            (
+                brave_params,
+                &taiwan_unfiltered,
+                &[("country", "tw"), ("safesearch", "off")],
+            ),
+            (brave_params, &english, &[]),
+        ];
+
+        for (engine_params, locale, expected) in cases {
+            let actual = engine_params(locale);
+            let actual: Vec<(&str, &str)> = actual
+                .iter()
+                .map(|(name, value)| (*name, value.as_str()))
+                .collect();
+            assert_eq!(actual, expected, "{}", locale.tag());
+        }
+    }
+
+    #[test]
+    fn appends_params_after_existing_ones() {
+        let params = [("hl", "pt".to_string()), ("gl", "BR".to_string())];
+
+        assert_eq!(
+            append_params("https://www.google.com/search?q=rust%20lang", &params),
+            "https://www.google.com/search?q=rust%20lang&hl=pt&gl=BR"
+        );
+        assert_eq!(
+            append_params("https://example.com/search", &params),
+            "https://example.com/search?hl=pt&gl=BR"
+        );
+        assert_eq!(
+            append_params("https://example.com/?q=a", &[("kl", "us en&x".to_string())]),
+            "https://example.com/?q=a&kl=us%20en%26x"
+        );
+        assert_eq!(
+            append_params("https://example.com/?q=a", &[]),
+            "https://example.com/?q=a"
+        );
+    }
+}
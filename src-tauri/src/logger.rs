@@ -1,11 +1,52 @@
 #![allow(unused)]
 
+use crate::persist::{self, Schema};
 use flexi_logger::{DeferredNow, Duplicate, FileSpec, Logger, WriteMode};
+use lazy_static::lazy_static;
 use log::Record;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::path::PathBuf;
+use std::sync::RwLock;
 use std::{fs, io::Write, path::Path, process::Command};
 use tauri::AppHandle;
 use tauri_plugin_opener::OpenerExt;
 
+/// How much of the user's search text ends up in the logs
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrivacyLevel {
+    /// Queries and URLs are logged as-is
+    Full,
+    /// Queries are replaced with a hash that is stable for the current session
+    #[default]
+    Hash,
+    /// Queries are removed entirely
+    Elide,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LogConfig {
+    #[serde(default)]
+    privacy_level: PrivacyLevel,
+}
+
+/// Version 1 added the versioned envelope
+const CONFIG_SCHEMA: Schema = Schema::new(&[persist::unchanged]);
+
+/// Not loaded lazily: reading the config logs, which needs the privacy level.
+/// Until `init` loads it, the default applies.
+static PRIVACY_LEVEL: RwLock<PrivacyLevel> = RwLock::new(PrivacyLevel::Hash);
+
+lazy_static! {
+    // Seeded per run, so hashes can be correlated within a log but not looked up across logs
+    static ref HASH_STATE: RandomState = RandomState::new();
+    static ref URL_PATTERN: Regex = Regex::new(r#"[a-zA-Z][a-zA-Z0-9+.-]*://[^\s'"]+"#).unwrap();
+    static ref QUOTED_PATTERN: Regex = Regex::new(r#"'[^']*'|"[^"]*""#).unwrap();
+}
+
 pub fn init() {
     let file_format = |write: &mut dyn Write, now: &mut DeferredNow, record: &Record| {
         write!(
@@ -54,6 +95,7 @@ pub fn init() {
         .start()
         .unwrap();
 
+    load_privacy_level();
     delete_old_log_files(3);
 }
 
@@ -117,8 +159,120 @@ pub fn open_log_directory(app_handle: AppHandle) -> Result<(), String> {
     Ok(())
 }
 
+fn get_config_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("zephyr")
+        .join("logging.json")
+}
+
+fn load_config() -> LogConfig {
+    match persist::read(&get_config_path(), &CONFIG_SCHEMA) {
+        Ok(config) => config.unwrap_or_default(),
+        Err(e) => {
+            error(&format!("Failed to load logging config: {}", e));
+            LogConfig::default()
+        }
+    }
+}
+
+/// Applies the privacy level from the config file
+pub fn load_privacy_level() {
+    apply_privacy_level(load_config().privacy_level);
+}
+
+/// Reads the privacy level after the config was edited by hand, reporting errors instead
+/// of recovering a backup
+pub fn read_privacy_level() -> Result<Option<PrivacyLevel>, String> {
+    persist::read_without_recovery::<LogConfig>(&get_config_path(), &CONFIG_SCHEMA)
+        .map(|config| config.map(|config| config.privacy_level))
+}
+
+/// Uses the privacy level for every following message, without saving it
+pub fn apply_privacy_level(level: PrivacyLevel) {
+    *PRIVACY_LEVEL.write().unwrap() = level;
+}
+
+pub fn privacy_level() -> PrivacyLevel {
+    *PRIVACY_LEVEL.read().unwrap()
+}
+
+pub fn set_privacy_level(level: PrivacyLevel) -> Result<(), String> {
+    let config = LogConfig {
+        privacy_level: level,
+    };
+    persist::write(&get_config_path(), &CONFIG_SCHEMA, &config)?;
+
+    apply_privacy_level(level);
+    Ok(())
+}
+
+/// Whether the text is already a redaction marker, possibly percent-encoded in a URL
+fn is_redacted(text: &str) -> bool {
+    let text = text.replace("%3C", "<").replace("%3E", ">");
+    text == "<redacted>" || (text.starts_with("<hash:") && text.ends_with('>'))
+}
+
+/// Redacts search text according to the privacy level
+pub fn redact_query(query: &str) -> String {
+    if is_redacted(query) {
+        return query.to_string();
+    }
+
+    match privacy_level() {
+        PrivacyLevel::Full => query.to_string(),
+        PrivacyLevel::Hash => format!("<hash:{:08x}>", HASH_STATE.hash_one(query) as u32),
+        PrivacyLevel::Elide => "<redacted>".to_string(),
+    }
+}
+
+/// Redacts the query string and fragment of a URL, keeping the host and path for debugging
+pub fn redact_url(url: &str) -> String {
+    if privacy_level() == PrivacyLevel::Full {
+        return url.to_string();
+    }
+
+    let Ok(mut parsed) = url::Url::parse(url) else {
+        return redact_query(url);
+    };
+
+    if let Some(query) = parsed.query().map(redact_query) {
+        parsed.set_query(Some(&query));
+    }
+    if let Some(fragment) = parsed.fragment().map(redact_query) {
+        parsed.set_fragment(Some(&fragment));
+    }
+
+    // Undo the percent-encoding of the redaction markers
+    parsed.to_string().replace("%3C", "<").replace("%3E", ">")
+}
+
+/// Redacts quoted text and URLs inside a free-form message
+pub fn redact_message(message: &str) -> String {
+    if privacy_level() == PrivacyLevel::Full {
+        return message.to_string();
+    }
+
+    let message = QUOTED_PATTERN.replace_all(message, |caps: &regex::Captures| {
+        let quoted = &caps[0];
+        let quote = &quoted[..1];
+        format!(
+            "{}{}{}",
+            quote,
+            redact_query(&quoted[1..quoted.len() - 1]),
+            quote
+        )
+    });
+
+    URL_PATTERN
+        .replace_all(&message, |caps: &regex::Captures| redact_url(&caps[0]))
+        .to_string()
+}
+
 #[tauri::command]
 pub fn log(message: String, level: &str) {
+    let message = redact_message(&message);
+
     match level {
         "debug" => log::debug!("UI: {}", message),
         "info" => log::info!("UI: {}", message),
@@ -128,22 +282,59 @@ pub fn log(message: String, level: &str) {
     }
 }
 
+// Messages are redacted here too, so search text can't reach the logs through a
+// message that forgot to redact it
+
 pub fn error(message: &str) {
-    log::error!("{}", message);
+    log::error!("{}", redact_message(message));
 }
 
 pub fn warn(message: &str) {
-    log::warn!("{}", message);
+    log::warn!("{}", redact_message(message));
 }
 
 pub fn info(message: &str) {
-    log::info!("{}", message);
+    log::info!("{}", redact_message(message));
 }
 
 pub fn debug(message: &str) {
-    log::debug!("{}", message);
+    log::debug!("{}", redact_message(message));
 }
 
 pub fn trace(message: &str) {
-    log::trace!("{}", message);
+    log::trace!("{}", redact_message(message));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tests run with the default privacy level, hashing
+
+    #[test]
+    fn redacts_quoted_text_and_url_queries() {
+        let message =
+            redact_message("Searching 'rust lang' at https://example.com/search?q=rust#top");
+
+        assert!(!message.contains("rust"), "{}", message);
+        assert!(message.starts_with("Searching '<hash:"), "{}", message);
+        assert!(
+            message.contains("https://example.com/search?<hash:"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn keeps_text_that_is_already_redacted() {
+        let query = redact_query("rust lang");
+        let url = redact_url("https://example.com/search?q=rust");
+
+        assert_eq!(redact_query(&query), query);
+        assert_eq!(redact_url(&url), url);
+        assert_eq!(
+            redact_message(&format!("Searching '{}' at {}", query, url)),
+            format!("Searching '{}' at {}", query, url)
+        );
+    }
 }
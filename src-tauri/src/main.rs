@@ -2,8 +2,7 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use std::panic;
-
-mod logger;
+use zephyr_lib::logger;
 
 fn main() {
     logger::init();
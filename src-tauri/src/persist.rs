@@ -0,0 +1,515 @@
+use crate::logger;
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Emitter};
+
+/// Rolling backups kept of each file unless its schema says otherwise
+pub const BACKUP_COUNT: usize = 3;
+
+/// Upgrades the data of a file by one version
+pub type Migration = fn(Value) -> Result<Value, String>;
+
+/// Current version of a persisted file and how to get there from older versions
+pub struct Schema {
+    pub version: u64,
+    /// `migrations[n]` upgrades data from version `n` to `n + 1`.
+    /// Version 0 is a file written before files had a version.
+    migrations: &'static [Migration],
+    /// Previous versions of the file kept to fall back to when it gets corrupted
+    backups: usize,
+}
+
+impl Schema {
+    pub const fn new(migrations: &'static [Migration]) -> Self {
+        Self {
+            version: migrations.len() as u64,
+            migrations,
+            backups: BACKUP_COUNT,
+        }
+    }
+
+    /// For files that can be rebuilt, like caches
+    pub const fn without_backups(self) -> Self {
+        Self { backups: 0, ..self }
+    }
+}
+
+/// How every file is stored, so the data can change shape without breaking older files
+#[derive(Serialize)]
+struct Envelope<'a, T> {
+    version: u64,
+    data: &'a T,
+}
+
+/// For versions that only changed how the file is stored, not the data
+pub fn unchanged(data: Value) -> Result<Value, String> {
+    Ok(data)
+}
+
+/// Splits a file into its version and data, files without an envelope are version 0
+fn open_envelope(value: Value) -> (u64, Value) {
+    match value {
+        Value::Object(mut map)
+            if map.len() == 2
+                && map.contains_key("data")
+                && map.get("version").is_some_and(Value::is_u64) =>
+        {
+            let version = map
+                .get("version")
+                .and_then(Value::as_u64)
+                .unwrap_or_default();
+            (version, map.remove("data").unwrap_or_default())
+        }
+        value => (0, value),
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Path of the copy kept before a file is migrated away from `version`
+fn get_backup_path(path: &Path, version: u64) -> PathBuf {
+    path.with_file_name(format!("{}.v{}.bak", file_name(path), version))
+}
+
+/// Copies the file before a migration overwrites it. An existing backup of the
+/// same version is kept, it's the closest to what that version wrote.
+pub fn backup_before_migration(path: &Path, version: u64) -> Result<(), String> {
+    let backup_path = get_backup_path(path, version);
+    if backup_path.exists() {
+        return Ok(());
+    }
+
+    fs::copy(path, &backup_path)
+        .map(|_| ())
+        .map_err(|e| format!("Failed to back up {}: {}", file_name(path), e))
+}
+
+/// Parses a file, upgrading the data when the file is from an older version.
+/// Also returns the version it was stored in and the migrated data.
+fn parse_envelope<T: DeserializeOwned>(
+    path: &Path,
+    json: &str,
+    schema: &Schema,
+) -> Result<(T, u64, Value), String> {
+    let value = serde_json::from_str::<Value>(json).map_err(|e| e.to_string())?;
+    let (version, mut data) = open_envelope(value);
+
+    if version > schema.version {
+        logger::warn(&format!(
+            "{} was saved by a newer version ({}), unknown fields are ignored and it won't be saved over",
+            file_name(path),
+            version
+        ));
+    }
+
+    for (from, migration) in schema.migrations.iter().enumerate().skip(version as usize) {
+        data = migration(data).map_err(|e| {
+            format!(
+                "Failed to migrate from version {} to {}: {}",
+                from,
+                from + 1,
+                e
+            )
+        })?;
+    }
+
+    let parsed = serde_json::from_value::<T>(data.clone()).map_err(|e| e.to_string())?;
+    Ok((parsed, version, data))
+}
+
+/// Saves the migrated data once a file from an older version has been read
+fn finish_migration(path: &Path, schema: &Schema, version: u64, data: &Value) {
+    if version >= schema.version {
+        return;
+    }
+
+    // The migrated data is still used when it can't be written, the migration runs again next time
+    let written =
+        backup_before_migration(path, version).and_then(|_| write_envelope(path, schema, data));
+
+    match written {
+        Ok(()) => logger::info(&format!(
+            "Migrated {} from version {} to {}",
+            file_name(path),
+            version,
+            schema.version
+        )),
+        Err(e) => logger::error(&format!(
+            "Failed to save migrated {}: {}",
+            file_name(path),
+            e
+        )),
+    }
+}
+
+/// Reads a file written by `write`, migrating it first when it's from an older version.
+/// A corrupted file is replaced by its newest valid backup. Returns `None` when the
+/// file doesn't exist.
+pub fn read<T: DeserializeOwned>(path: &Path, schema: &Schema) -> Result<Option<T>, String> {
+    read_validated(path, schema, |_| Ok(()))
+}
+
+/// Like `read`, but data that doesn't pass `validate` counts as corrupted
+pub fn read_validated<T: DeserializeOwned>(
+    path: &Path,
+    schema: &Schema,
+    validate: impl Fn(&T) -> Result<(), String>,
+) -> Result<Option<T>, String> {
+    // Restoring a backup would overwrite the newer file, so it's only reported
+    if is_newer(path, schema) {
+        let parsed = read_without_recovery::<T>(path, schema)?;
+        if let Some(parsed) = &parsed {
+            validate(parsed)?;
+        }
+        return Ok(parsed);
+    }
+
+    let parsed = read_with_recovery(path, schema.backups, |json| {
+        let parsed = parse_envelope::<T>(path, json, schema)?;
+        validate(&parsed.0)?;
+        Ok(parsed)
+    })?;
+
+    Ok(parsed.map(|(parsed, version, data)| {
+        finish_migration(path, schema, version, &data);
+        parsed
+    }))
+}
+
+/// Like `read`, but reports a corrupted file instead of falling back to a backup.
+/// Used for files edited by hand, where the user should fix their own change.
+pub fn read_without_recovery<T: DeserializeOwned>(
+    path: &Path,
+    schema: &Schema,
+) -> Result<Option<T>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let json = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let (parsed, version, data) = parse_envelope::<T>(path, &json, schema)?;
+    finish_migration(path, schema, version, &data);
+    Ok(Some(parsed))
+}
+
+/// Parses data in the format `write` uses, from any version, e.g. a copied file being imported
+pub fn from_str<T: DeserializeOwned>(json: &str, schema: &Schema) -> Result<T, String> {
+    parse_envelope::<T>(Path::new("Imported file"), json, schema).map(|(parsed, _, _)| parsed)
+}
+
+/// Formats the data the way `write` stores it
+pub fn to_string<T: Serialize>(schema: &Schema, data: &T) -> Result<String, String> {
+    serde_json::to_string_pretty(&Envelope {
+        version: schema.version,
+        data,
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Writes the data in the current version of the schema
+pub fn write<T: Serialize>(path: &Path, schema: &Schema, data: &T) -> Result<(), String> {
+    write_envelope(path, schema, data)
+}
+
+/// Whether the file was saved by a newer version of Zephyr. Those files are read-only,
+/// writing them in an older version would lose what the newer one added.
+fn is_newer(path: &Path, schema: &Schema) -> bool {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|json| serde_json::from_str::<Value>(&json).ok())
+        .is_some_and(|value| open_envelope(value).0 > schema.version)
+}
+
+fn write_envelope<T: Serialize>(path: &Path, schema: &Schema, data: &T) -> Result<(), String> {
+    if is_newer(path, schema) {
+        return Err(format!(
+            "{} was saved by a newer version of Zephyr and can't be changed by this one",
+            file_name(path)
+        ));
+    }
+
+    let json = to_string(schema, data)?;
+    write_file(path, json.as_bytes(), schema.backups)
+}
+
+/// Path of the `number`th newest rolling backup, starting at 1
+fn get_rolling_backup_path(path: &Path, number: usize) -> PathBuf {
+    path.with_file_name(format!("{}.{}.bak", file_name(path), number))
+}
+
+/// Shifts the rolling backups by one and copies the current file to the newest one
+fn rotate_backups(path: &Path, backups: usize) -> Result<(), String> {
+    for number in (1..backups).rev() {
+        let backup_path = get_rolling_backup_path(path, number);
+        if backup_path.exists() {
+            fs::rename(&backup_path, get_rolling_backup_path(path, number + 1))
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    fs::copy(path, get_rolling_backup_path(path, 1))
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Replaces the file without ever leaving a partially written one behind: the contents
+/// go to a temporary file that's flushed to disk and then renamed over the file.
+/// The previous contents are kept in `backups` rolling backups.
+pub fn write_file(path: &Path, contents: &[u8], backups: usize) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    let temp_path = path.with_file_name(format!("{}.tmp", file_name(path)));
+    let written = File::create(&temp_path).and_then(|mut file| {
+        file.write_all(contents)?;
+        file.sync_all()
+    });
+
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp_path);
+        return Err(format!("Failed to write {}: {}", file_name(path), e));
+    }
+
+    if backups > 0 && path.exists() {
+        // Losing a backup is better than not saving at all
+        if let Err(e) = rotate_backups(path, backups) {
+            logger::warn(&format!("Failed to back up {}: {}", file_name(path), e));
+        }
+    }
+
+    fs::rename(&temp_path, path).map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+        format!("Failed to replace {}: {}", file_name(path), e)
+    })?;
+
+    // The rename is only durable once the directory entry is on disk too
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        if let Err(e) = File::open(parent).and_then(|dir| dir.sync_all()) {
+            logger::warn(&format!(
+                "Failed to sync the folder of {}: {}",
+                file_name(path),
+                e
+            ));
+        }
+    }
+
+    WRITTEN_HASHES
+        .lock()
+        .unwrap()
+        .insert(path.to_path_buf(), hash_contents(contents));
+    Ok(())
+}
+
+fn hash_contents(contents: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Whether the file holds exactly what the app last wrote to it, so a change to it
+/// came from the app itself rather than from someone editing it
+pub fn is_own_write(path: &Path) -> bool {
+    let Some(written) = WRITTEN_HASHES.lock().unwrap().get(path).copied() else {
+        return false;
+    };
+
+    fs::read(path).is_ok_and(|contents| hash_contents(&contents) == written)
+}
+
+/// Reads and parses a file. When it doesn't parse, the newest backup that does takes
+/// its place and the user is told about it. A corrupted file without a valid backup
+/// is copied aside, so saving over it doesn't lose what's left of it.
+pub fn read_with_recovery<T>(
+    path: &Path,
+    backups: usize,
+    parse: impl Fn(&str) -> Result<T, String>,
+) -> Result<Option<T>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    let error = match String::from_utf8(bytes) {
+        Ok(contents) => match parse(&contents) {
+            Ok(parsed) => return Ok(Some(parsed)),
+            Err(e) => e,
+        },
+        Err(e) => e.to_string(),
+    };
+
+    let corrupt_path = path.with_file_name(format!("{}.corrupt", file_name(path)));
+    if let Err(e) = fs::copy(path, &corrupt_path) {
+        logger::error(&format!(
+            "Failed to keep a copy of corrupted {}: {}",
+            file_name(path),
+            e
+        ));
+    }
+
+    for number in 1..=backups {
+        let backup_path = get_rolling_backup_path(path, number);
+        let Ok(contents) = fs::read_to_string(&backup_path) else {
+            continue;
+        };
+        let Ok(parsed) = parse(&contents) else {
+            continue;
+        };
+
+        if let Err(e) = write_file(path, contents.as_bytes(), 0) {
+            logger::error(&format!(
+                "Failed to restore {} from its backup: {}",
+                file_name(path),
+                e
+            ));
+        }
+
+        report_recovery(RecoveredFile {
+            file: file_name(path),
+            backup: file_name(&backup_path),
+            error,
+            recovered_at: Utc::now(),
+        });
+        return Ok(Some(parsed));
+    }
+
+    Err(error)
+}
+
+/// A corrupted file that was replaced by one of its backups
+#[derive(Debug, Clone, Serialize)]
+pub struct RecoveredFile {
+    pub file: String,
+    pub backup: String,
+    /// Why the file couldn't be read
+    pub error: String,
+    pub recovered_at: DateTime<Utc>,
+}
+
+lazy_static! {
+    // Files are read before the window exists, so recoveries wait here until they're seen
+    static ref RECOVERED_FILES: Mutex<Vec<RecoveredFile>> = Mutex::new(Vec::new());
+    // Hash of the contents last written to each file, see `is_own_write`
+    static ref WRITTEN_HASHES: Mutex<HashMap<PathBuf, u64>> = Mutex::new(HashMap::new());
+}
+
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+
+/// Lets recoveries from now on be sent to the UI as they happen
+pub fn init(app_handle: &AppHandle) {
+    let _ = APP_HANDLE.set(app_handle.clone());
+}
+
+fn report_recovery(recovered: RecoveredFile) {
+    logger::warn(&format!(
+        "{} was corrupted and has been restored from {}: {}",
+        recovered.file, recovered.backup, recovered.error
+    ));
+
+    RECOVERED_FILES.lock().unwrap().push(recovered.clone());
+
+    if let Some(app_handle) = APP_HANDLE.get() {
+        if let Err(e) = app_handle.emit("file-recovered", &recovered) {
+            logger::error(&format!("Failed to send file recovery: {}", e));
+        }
+    }
+}
+
+/// Files restored from a backup that the user hasn't dismissed yet
+#[tauri::command]
+pub fn get_recovered_files() -> Vec<RecoveredFile> {
+    RECOVERED_FILES.lock().unwrap().clone()
+}
+
+#[tauri::command]
+pub fn dismiss_recovered_files() {
+    RECOVERED_FILES.lock().unwrap().clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: Schema = Schema::new(&[unchanged]);
+
+    fn temp_file(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("zephyr-persist-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn migrates_files_without_envelope() {
+        let path = temp_file("old.json");
+        fs::write(&path, r#"{"name": "old"}"#).unwrap();
+
+        let data: Value = read(&path, &SCHEMA).unwrap().unwrap();
+
+        assert_eq!(data["name"], "old");
+        let saved: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved["version"], 1);
+        assert!(get_backup_path(&path, 0).exists());
+    }
+
+    #[test]
+    fn tells_own_writes_from_edits() {
+        let path = temp_file("own.json");
+        assert!(!is_own_write(&path));
+
+        write(&path, &SCHEMA, &serde_json::json!({"name": "saved"})).unwrap();
+        assert!(is_own_write(&path));
+
+        fs::write(&path, r#"{"version": 1, "data": {"name": "edited"}}"#).unwrap();
+        assert!(!is_own_write(&path));
+    }
+
+    #[test]
+    fn reads_newer_files_without_saving_over_them() {
+        let path = temp_file("newer.json");
+        let newer = r#"{"version": 5, "data": {"name": "newer", "added": true}}"#;
+        fs::write(&path, newer).unwrap();
+
+        let data: Value = read(&path, &SCHEMA).unwrap().unwrap();
+        assert_eq!(data["name"], "newer");
+
+        let error = write(&path, &SCHEMA, &data).unwrap_err();
+        assert!(error.contains("newer version"), "{}", error);
+        assert_eq!(fs::read_to_string(&path).unwrap(), newer);
+    }
+
+    #[test]
+    fn does_not_restore_backups_over_newer_files() {
+        let path = temp_file("newer-invalid.json");
+        fs::write(&path, r#"{"name": "backup"}"#).unwrap();
+        write(&path, &SCHEMA, &serde_json::json!({"name": "current"})).unwrap();
+        let newer = r#"{"version": 5, "data": {"name": 5}}"#;
+        fs::write(&path, newer).unwrap();
+
+        let read = read_validated::<Value>(&path, &SCHEMA, |data| {
+            data["name"]
+                .as_str()
+                .map(|_| ())
+                .ok_or("name isn't text".to_string())
+        });
+
+        assert!(read.is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), newer);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::detectors::fill_template;
+use crate::logger;
+use regex::Regex;
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+
+/// A user-defined `pattern -> url template` routing rule
+pub struct RoutingRule {
+    pub pattern: Regex,
+    pub url: String,
+}
+
+impl RoutingRule {
+    pub fn new(pattern: &str, url: &str) -> Result<Self, String> {
+        let pattern = Regex::new(pattern).map_err(|e| format!("Invalid regex: {}", e))?;
+
+        Ok(Self {
+            pattern,
+            url: url.trim().to_string(),
+        })
+    }
+
+    /// Fills the URL template with the capture groups if the input matches
+    pub fn resolve(&self, input: &str) -> Option<String> {
+        self.pattern
+            .captures(input)
+            .map(|captures| fill_template(&self.url, &captures))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Rules loaded from the rules file, in the order they are evaluated
+#[derive(Default)]
+pub struct RuleSet {
+    pub rules: Vec<RoutingRule>,
+    pub errors: Vec<RuleError>,
+}
+
+impl RuleSet {
+    /// Parses the rules file format: one `pattern -> url` rule per line,
+    /// blank lines and lines starting with `#` are ignored. The URL is split off at the
+    /// last ` -> `, URLs can't contain spaces but patterns can.
+    pub fn parse(content: &str) -> Self {
+        let mut rule_set = RuleSet::default();
+
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let rule = match line.rsplit_once(" -> ") {
+                Some((pattern, url)) => RoutingRule::new(pattern.trim(), url),
+                None => Err("Expected a rule in the form `pattern -> url`".to_string()),
+            };
+
+            match rule {
+                Ok(rule) => rule_set.rules.push(rule),
+                Err(message) => rule_set.errors.push(RuleError {
+                    line: index + 1,
+                    message,
+                }),
+            }
+        }
+
+        rule_set
+    }
+
+    /// Returns the URL of the first rule matching the input
+    pub fn resolve(&self, input: &str) -> Option<String> {
+        let input = input.trim();
+        self.rules.iter().find_map(|rule| rule.resolve(input))
+    }
+}
+
+pub(crate) fn get_rules_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("zephyr")
+        .join("rules.txt")
+}
+
+/// Reads the rules file, reporting errors instead of falling back to no rules.
+/// Invalid lines are skipped and listed in the rule set's errors.
+pub fn read_rules() -> Result<RuleSet, String> {
+    let rules_path = get_rules_path();
+
+    if !rules_path.exists() {
+        return Ok(RuleSet::default());
+    }
+
+    fs::read_to_string(&rules_path)
+        .map(|content| RuleSet::parse(&content))
+        .map_err(|e| format!("Failed to read routing rules: {}", e))
+}
+
+pub fn load_rules() -> RuleSet {
+    match read_rules() {
+        Ok(rule_set) => {
+            log_rule_set(&rule_set);
+            rule_set
+        }
+        Err(e) => {
+            logger::error(&e);
+            RuleSet::default()
+        }
+    }
+}
+
+/// Logs the skipped lines and how many rules are in use, for both loading and reloading
+pub fn log_rule_set(rule_set: &RuleSet) {
+    for error in &rule_set.errors {
+        logger::warn(&format!(
+            "Skipping routing rule on line {}: {}",
+            error.line, error.message
+        ));
+    }
+
+    logger::info(&format!("Loaded {} routing rules", rule_set.rules.len()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_the_url_off_at_the_last_arrow() {
+        let rule_set = RuleSet::parse(
+            r"^(?P<from>\w+) -> (?P<to>\w+)$ -> https://example.com/{{from}}/{{to}}",
+        );
+
+        assert!(rule_set.errors.is_empty());
+        assert_eq!(
+            rule_set.resolve("a -> b").as_deref(),
+            Some("https://example.com/a/b")
+        );
+    }
+
+    #[test]
+    fn encodes_captures_in_the_url() {
+        let rule_set = RuleSet::parse(r"^go (?P<term>.+)$ -> https://example.com/?q={{term}}");
+
+        assert_eq!(
+            rule_set.resolve("go a&b c").as_deref(),
+            Some("https://example.com/?q=a%26b%20c")
+        );
+    }
+
+    #[test]
+    fn skips_invalid_lines_and_keeps_the_rest() {
+        let rule_set = RuleSet::parse(
+            "# comment\n\n^(unclosed -> https://example.com\nno arrow\n^ok$ -> https://example.com/ok",
+        );
+
+        assert_eq!(rule_set.rules.len(), 1);
+        let lines: Vec<usize> = rule_set.errors.iter().map(|error| error.line).collect();
+        assert_eq!(lines, vec![3, 4]);
+        assert_eq!(
+            rule_set.resolve("ok").as_deref(),
+            Some("https://example.com/ok")
+        );
+    }
+}
@@ -1,70 +1,414 @@
-use crate::bangs::{self, Bang};
+use crate::bangs::{self, Bang, BangQuery, KeywordConfig};
+use crate::connectivity;
+use crate::detectors::{DetectorConfig, DetectorPipeline};
+use crate::history::{self, HistoryEntry, SearchHistory};
+use crate::locale::LocaleSettings;
 use crate::logger;
+use crate::rules::{RoutingRule, RuleError, RuleSet};
+use crate::settings::{SearchEngine, SettingsState};
+use crate::suggestions::{
+    self, ProviderHealth, ProviderRace, ProviderStats, Suggestion, SuggestionCache,
+    SuggestionConfig, SuggestionProvider, SuggestionUpdate,
+};
 use std::collections::HashMap;
-use std::sync::Mutex;
-use tauri::AppHandle;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use tauri::State;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::watch;
 use url::Url;
 
 pub struct BangState {
     pub bangs: Mutex<HashMap<String, Bang>>,
+    pub keywords: Mutex<KeywordConfig>,
 }
 
+pub struct DetectorState {
+    pub config: Mutex<DetectorConfig>,
+    pub pipeline: Mutex<DetectorPipeline>,
+}
+
+pub struct RuleState {
+    pub rules: Mutex<RuleSet>,
+}
+
+pub struct HistoryState {
+    pub history: Mutex<SearchHistory>,
+}
+
+pub struct LocaleState {
+    pub settings: Mutex<LocaleSettings>,
+}
+
+pub struct SuggestionState {
+    pub config: Mutex<SuggestionConfig>,
+    pub providers: Mutex<Vec<Arc<dyn SuggestionProvider>>>,
+    pub cache: Arc<SuggestionCache>,
+    pub stats: Arc<ProviderStats>,
+    /// Sequence number of the newest suggestion request from the search box
+    pub latest_seq: watch::Sender<u64>,
+}
+
+/// Number of history entries merged into the suggestions
+const HISTORY_SUGGESTION_LIMIT: usize = 3;
+
 #[tauri::command]
-pub async fn get_search_suggestions(query: String) -> Result<Vec<String>, String> {
+#[allow(clippy::too_many_arguments)]
+pub async fn get_search_suggestions(
+    app_handle: AppHandle,
+    query: String,
+    limit: Option<usize>,
+    seq: Option<u64>,
+    bang_state: State<'_, BangState>,
+    detector_state: State<'_, DetectorState>,
+    rule_state: State<'_, RuleState>,
+    history_state: State<'_, HistoryState>,
+    locale_state: State<'_, LocaleState>,
+    suggestion_state: State<'_, SuggestionState>,
+) -> Result<Option<Vec<Suggestion>>, String> {
     if query.trim().is_empty() {
-        return Ok(vec![]);
+        return Ok(Some(vec![]));
+    }
+
+    // Requests are numbered by the search box, so a newer one makes this one obsolete
+    let mut latest_seq = suggestion_state.latest_seq.subscribe();
+    if let Some(seq) = seq {
+        supersede_suggestions(&suggestion_state, seq);
     }
 
-    logger::info(&format!("Getting suggestions: '{}'", query));
+    let limit = limit.unwrap_or(8);
+
+    logger::info(&format!(
+        "Getting suggestions: '{}'",
+        logger::redact_query(&query)
+    ));
+
+    let direct_suggestions = get_direct_suggestions(&query, &detector_state, &rule_state);
+
+    let history_suggestions: Vec<Suggestion> = {
+        let history_lock = history_state.history.lock().unwrap();
+        let entries = history::query_history(&history_lock, &query, HISTORY_SUGGESTION_LIMIT);
+        let max_frecency = entries.first().map_or(0.0, |entry| entry.frecency);
+
+        entries
+            .iter()
+            .map(|entry| Suggestion::history(entry, max_frecency))
+            .collect()
+    };
+
+    // Suggestions for a query with a bang come from the bang's own provider when there is one
+    let (bang_suggestions, providers, bang_query) = {
+        let bangs_lock = bang_state.bangs.lock().unwrap();
+        let bang_suggestions = get_bang_suggestions(&bangs_lock, &query, limit);
+        let bang_query = bangs::split_bang(&bangs_lock, &query).or_else(|| {
+            let keywords_lock = bang_state.keywords.lock().unwrap();
+            bangs::split_keyword(&bangs_lock, &keywords_lock, &query)
+        });
+        let bang_provider = bang_query
+            .as_ref()
+            .and_then(|bang_query| bangs_lock.get(&bang_query.bang))
+            .and_then(suggestions::provider_for_bang);
+
+        let providers = match bang_provider {
+            Some(provider) => vec![provider],
+            None => suggestion_state.providers.lock().unwrap().clone(),
+        };
+        (bang_suggestions, providers, bang_query)
+    };
+
+    let remote_query = bang_query
+        .as_ref()
+        .map_or(query.as_str(), |bang_query| bang_query.term.as_str());
 
-    let url = format!(
-        // Client needs to be specified of Google gets angry
-        "https://suggestqueries.google.com/complete/search?client=chrome&q={}",
-        urlencoding::encode(&query)
+    // Skip remote suggestions while an unfinished bang is being typed, there's no search term,
+    // or there's no connection
+    let typing_bang = !bang_suggestions.is_empty() && bang_query.is_none();
+    let local_suggestions = vec![direct_suggestions, bang_suggestions, history_suggestions];
+    if remote_query.trim().is_empty() || typing_bang || !connectivity::is_online() {
+        return Ok(Some(suggestions::merge_suggestions(
+            local_suggestions,
+            limit,
+        )));
+    }
+
+    let deadline = suggestion_state.config.lock().unwrap().deadline();
+    let locale = locale_state.settings.lock().unwrap().clone();
+    let mut race = ProviderRace::start(
+        suggestion_state.stats.rank(providers, deadline),
+        remote_query,
+        &locale,
+        suggestion_state.cache.clone(),
+        suggestion_state.stats.clone(),
+        deadline,
     );
 
-    match reqwest::get(&url).await {
-        Ok(response) => {
-            if response.status().is_success() {
-                match response.json::<serde_json::Value>().await {
-                    Ok(data) => {
-                        // Google returns an array where the second element contains the suggestions
-                        if let Some(suggestions) = data.get(1).and_then(|v| v.as_array()) {
-                            let result: Vec<String> = suggestions
-                                .iter()
-                                .filter_map(|s| s.as_str().map(|s| s.to_string()))
-                                .collect();
-                            logger::info(&format!("Found {} suggestions", result.len()));
-                            return Ok(result);
-                        }
-                        Ok(vec![])
-                    }
-                    Err(e) => Err(format!("Failed to parse response: {}", e)),
-                }
-            } else {
-                Err(format!("Request failed with status: {}", response.status()))
+    match seq {
+        Some(seq) => tokio::select! {
+            _ = race.collect_until(deadline) => {}
+            _ = latest_seq.wait_for(|latest| *latest > seq) => {
+                // Dropping the race cancels the HTTP requests
+                logger::debug(&format!("Dropping superseded suggestion request {}", seq));
+                return Ok(None);
             }
+        },
+        None => race.collect_until(deadline).await,
+    }
+
+    if seq.is_some_and(|seq| *latest_seq.borrow() > seq) {
+        return Ok(None);
+    }
+
+    let suggestions =
+        merge_remote_suggestions(local_suggestions.clone(), &race, bang_query.as_ref(), limit);
+
+    // Only requests with a sequence number can be matched up with late results
+    if let Some(seq) = seq.filter(|_| !race.is_finished()) {
+        backfill_suggestions(
+            app_handle,
+            race,
+            local_suggestions,
+            bang_query,
+            limit,
+            seq,
+            latest_seq,
+        );
+    }
+
+    Ok(Some(suggestions))
+}
+
+fn merge_remote_suggestions(
+    mut sources: Vec<Vec<Suggestion>>,
+    race: &ProviderRace,
+    bang_query: Option<&BangQuery>,
+    limit: usize,
+) -> Vec<Suggestion> {
+    let remote_suggestions = race
+        .suggestions()
+        .into_iter()
+        .enumerate()
+        .map(|(rank, suggestion)| {
+            let text = match bang_query {
+                Some(bang_query) => bang_query.with_term(&suggestion),
+                None => suggestion,
+            };
+            Suggestion::remote(text, rank)
+        })
+        .collect();
+
+    sources.push(remote_suggestions);
+    suggestions::merge_suggestions(sources, limit)
+}
+
+/// Makes every suggestion request numbered below `seq` obsolete
+fn supersede_suggestions(suggestion_state: &SuggestionState, seq: u64) {
+    suggestion_state.latest_seq.send_if_modified(|latest| {
+        let newer = seq > *latest;
+        if newer {
+            *latest = seq;
+        }
+        newer
+    });
+}
+
+/// Stops the suggestion requests numbered below `seq`, aborting their provider requests
+/// and late updates, e.g. once the search box is cleared
+#[tauri::command]
+pub fn cancel_suggestions(suggestion_state: State<'_, SuggestionState>, seq: u64) {
+    supersede_suggestions(&suggestion_state, seq);
+}
+
+/// Sends the suggestions to the UI again once the providers that missed the deadline have
+/// answered, unless a newer request came in first
+fn backfill_suggestions(
+    app_handle: AppHandle,
+    mut race: ProviderRace,
+    local_suggestions: Vec<Vec<Suggestion>>,
+    bang_query: Option<BangQuery>,
+    limit: usize,
+    seq: u64,
+    mut latest_seq: watch::Receiver<u64>,
+) {
+    tauri::async_runtime::spawn(async move {
+        tokio::select! {
+            _ = race.collect_all() => {}
+            _ = latest_seq.wait_for(|latest| *latest > seq) => return,
+        }
+
+        let suggestions =
+            merge_remote_suggestions(local_suggestions, &race, bang_query.as_ref(), limit);
+
+        if let Err(e) =
+            app_handle.emit("suggestions-updated", SuggestionUpdate { seq, suggestions })
+        {
+            logger::error(&format!("Failed to send late suggestions: {}", e));
         }
-        Err(e) => Err(format!("Failed to fetch suggestions: {}", e)),
+    });
+}
+
+/// Suggestions for input that opens something directly rather than searching for it
+fn get_direct_suggestions(
+    query: &str,
+    detector_state: &State<DetectorState>,
+    rule_state: &State<RuleState>,
+) -> Vec<Suggestion> {
+    let query = query.trim();
+
+    if let Some(path) = parse_local_path(query) {
+        if !path.exists() {
+            return vec![];
+        }
+        let path_str = path.to_string_lossy().to_string();
+        return vec![Suggestion::path(query, path_str, path.is_dir())];
+    }
+
+    if let Some(url) = rule_state.rules.lock().unwrap().resolve(query) {
+        return vec![Suggestion::detected(query, "rule", url)];
+    }
+
+    if let Some(detection) = detector_state.pipeline.lock().unwrap().detect(query) {
+        return vec![Suggestion::detected(
+            query,
+            &detection.detector,
+            detection.url,
+        )];
     }
+
+    if is_url(query) {
+        return vec![Suggestion::url(query, ensure_url_scheme(query.to_string()))];
+    }
+
+    vec![]
+}
+
+/// Completes a `!bang` while it is the last word being typed
+fn get_bang_suggestions(
+    bangs: &HashMap<String, Bang>,
+    query: &str,
+    limit: usize,
+) -> Vec<Suggestion> {
+    if query.ends_with(char::is_whitespace) {
+        return vec![];
+    }
+
+    let last_word_start = query
+        .rfind(char::is_whitespace)
+        .map_or(0, |index| index + 1);
+    let (before, last_word) = query.split_at(last_word_start);
+
+    let Some(prefix) = last_word.strip_prefix('!') else {
+        return vec![];
+    };
+
+    bangs::complete_bang(bangs, prefix, limit)
+        .iter()
+        .enumerate()
+        .map(|(rank, (id, name))| Suggestion::bang(format!("{}!{} ", before, id), id, name, rank))
+        .collect()
+}
+
+/// Where a query takes the user once it has been resolved
+enum SearchTarget {
+    Path(PathBuf),
+    Url(String),
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn search(
     app_handle: AppHandle,
     query: String,
     bang_state: State<'_, BangState>,
+    detector_state: State<'_, DetectorState>,
+    rule_state: State<'_, RuleState>,
+    history_state: State<'_, HistoryState>,
+    locale_state: State<'_, LocaleState>,
+    settings_state: State<'_, SettingsState>,
 ) -> Result<(), String> {
-    let url = if is_url(&query) {
-        ensure_url_scheme(query)
-    } else {
-        get_bang_redirect_url(query, &bang_state)
+    let incognito_query = {
+        let history_lock = history_state.history.lock().unwrap();
+        history::strip_incognito_prefix(&history_lock, &query)
+    };
+    let is_incognito = incognito_query.is_some();
+    let query = incognito_query.unwrap_or(query);
+
+    let locale = locale_state.settings.lock().unwrap().clone();
+    let engine = settings_state.get().default_engine;
+    let target = resolve_search_target(
+        &query,
+        &bang_state,
+        &detector_state,
+        &rule_state,
+        &engine,
+        &locale,
+    );
+
+    let opened = match target {
+        SearchTarget::Path(path) => {
+            open_path(&app_handle, &path)?;
+            path.to_string_lossy().to_string()
+        }
+        SearchTarget::Url(url) => {
+            logger::info(&format!("Opening URL: {}", logger::redact_url(&url)));
+            open_url(app_handle, &url)?;
+            url
+        }
     };
 
-    logger::info(&format!("Opening URL: {}", url));
+    if !is_incognito {
+        record_history(&history_state, &query, &opened);
+    }
 
-    open_url(app_handle, &url)
+    Ok(())
+}
+
+fn resolve_search_target(
+    query: &str,
+    bang_state: &State<BangState>,
+    detector_state: &State<DetectorState>,
+    rule_state: &State<RuleState>,
+    engine: &SearchEngine,
+    locale: &LocaleSettings,
+) -> SearchTarget {
+    // A leading `?` forces a plain web search, skipping paths, rules, detectors and bangs
+    if let Some(plain_query) = query.trim_start().strip_prefix('?') {
+        return SearchTarget::Url(engine.search_url(plain_query.trim(), locale));
+    }
+
+    if let Some(path) = parse_local_path(query) {
+        return SearchTarget::Path(path);
+    }
+
+    if let Some(url) = rule_state.rules.lock().unwrap().resolve(query) {
+        logger::info("Input matched a routing rule");
+        return SearchTarget::Url(url);
+    }
+
+    if let Some(detection) = detector_state.pipeline.lock().unwrap().detect(query) {
+        logger::info(&format!(
+            "Input recognized by '{}' detector",
+            detection.detector
+        ));
+        return SearchTarget::Url(detection.url);
+    }
+
+    if is_url(query) {
+        return SearchTarget::Url(ensure_url_scheme(query.to_string()));
+    }
+
+    SearchTarget::Url(get_bang_redirect_url(
+        query.to_string(),
+        bang_state,
+        engine,
+        locale,
+    ))
+}
+
+fn record_history(history_state: &State<HistoryState>, query: &str, url: &str) {
+    let mut history_lock = history_state.history.lock().unwrap();
+    if let Err(e) = history::record_search(&mut history_lock, query, url) {
+        logger::error(&format!("Failed to record search history: {}", e));
+    }
 }
 
 #[tauri::command]
@@ -77,6 +421,62 @@ pub fn open_url(app_handle: AppHandle, url: &str) -> Result<(), String> {
         .map_err(|e| format!("Failed to open URL: {}", e))
 }
 
+/// Opens a local file with the system default app, or a directory with the file manager
+fn open_path(app_handle: &AppHandle, path: &Path) -> Result<(), String> {
+    use tauri_plugin_opener::OpenerExt;
+
+    if !path.exists() {
+        return Err(format!("Path not found: {}", path.display()));
+    }
+
+    logger::info(&format!(
+        "Opening path: {}",
+        logger::redact_query(&path.to_string_lossy())
+    ));
+
+    app_handle
+        .opener()
+        .open_path(path.to_string_lossy(), None::<&str>)
+        .map_err(|e| format!("Failed to open path: {}", e))
+}
+
+/// Interprets the input as a local path if it is an absolute path,
+/// a home-relative path (`~/...`) or a `file://` URI
+fn parse_local_path(input: &str) -> Option<PathBuf> {
+    // Paths copied from a file manager are often wrapped in quotes
+    let input = input.trim().trim_matches('"');
+
+    if input.len() > 7
+        && input
+            .get(..7)
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case("file://"))
+    {
+        return match Url::parse(input).map(|url| url.to_file_path()) {
+            Ok(Ok(path)) => Some(path),
+            // Still treat it as a path so the user gets a "not found" error
+            _ => Some(PathBuf::from(&input[7..])),
+        };
+    }
+
+    if input == "~" {
+        return dirs::home_dir();
+    }
+
+    if let Some(rest) = input
+        .strip_prefix("~/")
+        .or_else(|| input.strip_prefix("~\\"))
+    {
+        return dirs::home_dir().map(|home| home.join(rest));
+    }
+
+    let path = Path::new(input);
+    if path.is_absolute() {
+        return Some(path.to_path_buf());
+    }
+
+    None
+}
+
 fn is_url(input: &str) -> bool {
     let input = input.trim();
 
@@ -108,24 +508,26 @@ fn ensure_url_scheme(url: String) -> String {
     format!("https://{}", url)
 }
 
-fn get_bang_redirect_url(query: String, bang_state: &State<BangState>) -> String {
-    // Check if the query contains a bang pattern (!something)
-    if let Some(bang_pos) = query.rfind('!') {
-        let (search_term, bang_part) = query.split_at(bang_pos);
-        let bang = &bang_part[1..]; // Remove the ! character
+fn get_bang_redirect_url(
+    query: String,
+    bang_state: &State<BangState>,
+    engine: &SearchEngine,
+    locale: &LocaleSettings,
+) -> String {
+    // Check if the query contains a known bang, before or after the search term
+    let bangs_lock = bang_state.bangs.lock().unwrap();
+    if let Some(url) = bangs::get_query_bang_url(&bangs_lock, &query) {
+        return url;
+    }
 
-        // Try to find the bang in our database
-        let bangs_lock = bang_state.bangs.lock().unwrap();
-        if let Some(url) = bangs::get_bang_url(&bangs_lock, bang, search_term.trim()) {
-            return url;
-        }
+    // Check if the query starts with a keyword (gh something)
+    let keywords_lock = bang_state.keywords.lock().unwrap();
+    if let Some(url) = bangs::get_keyword_url(&bangs_lock, &keywords_lock, &query) {
+        return url;
     }
 
-    // Default to Google search if no bang or unrecognized bang
-    format!(
-        "https://www.google.com/search?q={}",
-        urlencoding::encode(&query)
-    )
+    // Default to the configured engine if no bang or unrecognized bang
+    engine.search_url(&query, locale)
 }
 
 #[tauri::command]
@@ -148,6 +550,7 @@ pub async fn refresh_bangs(
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub fn add_custom_bang(
     app_handle: AppHandle,
     bang_state: State<'_, BangState>,
@@ -156,6 +559,8 @@ pub fn add_custom_bang(
     search_url: String,
     home_url: String,
     category: String,
+    suggest_url: Option<String>,
+    icon_url: Option<String>,
 ) -> Result<(), String> {
     let bang = bangs::Bang {
         id,
@@ -164,6 +569,8 @@ pub fn add_custom_bang(
         home_url,
         category,
         is_custom: true,
+        suggest_url: suggest_url.filter(|url| !url.trim().is_empty()),
+        icon_url: icon_url.filter(|url| !url.trim().is_empty()),
     };
 
     let mut bangs_lock = bang_state.bangs.lock().unwrap();
@@ -180,6 +587,142 @@ pub fn delete_custom_bang(
     bangs::delete_custom_bang(&app_handle, &mut bangs_lock, &bang_id)
 }
 
+/// Changes made to custom bangs, newest first
+#[tauri::command]
+pub fn list_bang_revisions() -> Vec<bangs::RevisionSummary> {
+    bangs::list_bang_revisions()
+}
+
+#[tauri::command]
+pub fn diff_bang_revisions(from_id: u64, to_id: u64) -> Result<bangs::RevisionDiff, String> {
+    bangs::diff_bang_revisions(from_id, to_id)
+}
+
+#[tauri::command]
+pub fn restore_bang_revision(
+    app_handle: AppHandle,
+    bang_state: State<'_, BangState>,
+    revision_id: u64,
+) -> Result<(), String> {
+    let mut bangs_lock = bang_state.bangs.lock().unwrap();
+    bangs::restore_bang_revision(&app_handle, &mut bangs_lock, revision_id)
+}
+
+/// Reverts the last destructive change to custom bangs, shortly after it was made
+#[tauri::command]
+pub fn undo_bang_change(
+    app_handle: AppHandle,
+    bang_state: State<'_, BangState>,
+) -> Result<(), String> {
+    let mut bangs_lock = bang_state.bangs.lock().unwrap();
+    bangs::undo_bang_change(&app_handle, &mut bangs_lock)
+}
+
+/// Writes the custom bangs to a file, returning how many were exported
+#[tauri::command]
+pub fn export_bangs(
+    app_handle: AppHandle,
+    path: String,
+    format: bangs::BangFormat,
+) -> Result<usize, String> {
+    bangs::export_bangs(&app_handle, Path::new(&path), format)
+}
+
+/// Imports bangs from a file. With `dry_run`, only reports which bangs are new,
+/// conflicting or identical without changing anything.
+#[tauri::command]
+pub fn import_bangs(
+    app_handle: AppHandle,
+    bang_state: State<'_, BangState>,
+    path: String,
+    format: Option<bangs::BangFormat>,
+    strategy: Option<bangs::MergeStrategy>,
+    dry_run: bool,
+) -> Result<bangs::ImportPreview, String> {
+    let mut bangs_lock = bang_state.bangs.lock().unwrap();
+    bangs::import_bangs(
+        &app_handle,
+        &mut bangs_lock,
+        Path::new(&path),
+        format,
+        strategy.unwrap_or_default(),
+        dry_run,
+    )
+}
+
+/// Chrome, Edge, Brave and Firefox profiles with search engines to import
+#[tauri::command]
+pub fn find_browser_profiles() -> Vec<bangs::BrowserProfile> {
+    bangs::find_browser_profiles()
+}
+
+/// Imports a browser profile's keyword search engines. With `dry_run`, only previews them.
+#[tauri::command]
+pub fn import_browser_bangs(
+    app_handle: AppHandle,
+    bang_state: State<'_, BangState>,
+    path: String,
+    strategy: Option<bangs::MergeStrategy>,
+    dry_run: bool,
+) -> Result<bangs::ImportPreview, String> {
+    let mut bangs_lock = bang_state.bangs.lock().unwrap();
+    bangs::import_browser_bangs(
+        &app_handle,
+        &mut bangs_lock,
+        Path::new(&path),
+        strategy.unwrap_or_default(),
+        dry_run,
+    )
+}
+
+/// Imports web searches from Alfred, Raycast or Flow Launcher. With `dry_run`, only previews them.
+#[tauri::command]
+pub fn import_launcher_bangs(
+    app_handle: AppHandle,
+    bang_state: State<'_, BangState>,
+    path: String,
+    launcher: Option<bangs::Launcher>,
+    strategy: Option<bangs::MergeStrategy>,
+    dry_run: bool,
+) -> Result<bangs::ImportPreview, String> {
+    let mut bangs_lock = bang_state.bangs.lock().unwrap();
+    bangs::import_launcher_bangs(
+        &app_handle,
+        &mut bangs_lock,
+        Path::new(&path),
+        launcher,
+        strategy.unwrap_or_default(),
+        dry_run,
+    )
+}
+
+/// Writes bangs as Firefox or Chromium search engine policies, or as OpenSearch
+/// descriptions into the directory at `path`. Without `bang_ids`, the custom bangs
+/// are exported.
+#[tauri::command]
+pub fn export_browser_policies(
+    bang_state: State<'_, BangState>,
+    bang_ids: Vec<String>,
+    format: bangs::PolicyFormat,
+    path: String,
+) -> Result<bangs::PolicyExport, String> {
+    let bangs_lock = bang_state.bangs.lock().unwrap();
+    bangs::export_browser_policies(&bangs_lock, &bang_ids, format, Path::new(&path))
+}
+
+/// Makes a custom bang from a site's OpenSearch description, given its URL, the URL of a
+/// page linking to it, or a file. The bang isn't saved, it's returned for review first.
+#[tauri::command]
+pub async fn add_bang_from_opensearch(
+    bang_state: State<'_, BangState>,
+    url_or_file: String,
+) -> Result<bangs::Bang, String> {
+    bangs::bang_from_opensearch(&url_or_file, |id| {
+        bang_state.bangs.lock().unwrap().contains_key(id)
+    })
+    .await
+}
+
 #[tauri::command]
 pub async fn clear_bangs_cache(
     app_handle: AppHandle,
@@ -192,3 +735,63 @@ pub async fn clear_bangs_cache(
 
     Ok(())
 }
+
+#[tauri::command]
+pub fn get_routing_rule_errors(rule_state: State<'_, RuleState>) -> Vec<RuleError> {
+    rule_state.rules.lock().unwrap().errors.clone()
+}
+
+/// Tests a single rule against sample input, returning the URL it would open
+#[tauri::command]
+pub fn test_routing_rule(
+    pattern: String,
+    url: String,
+    input: String,
+) -> Result<Option<String>, String> {
+    let rule = RoutingRule::new(&pattern, &url)?;
+    Ok(rule.resolve(input.trim()))
+}
+
+#[tauri::command]
+pub fn get_search_history(
+    history_state: State<'_, HistoryState>,
+    prefix: String,
+    limit: Option<usize>,
+) -> Vec<HistoryEntry> {
+    let history_lock = history_state.history.lock().unwrap();
+    history::query_history(&history_lock, &prefix, limit.unwrap_or(20))
+}
+
+#[tauri::command]
+pub fn delete_history_entry(
+    history_state: State<'_, HistoryState>,
+    query: String,
+) -> Result<(), String> {
+    let mut history_lock = history_state.history.lock().unwrap();
+    history::delete_entry(&mut history_lock, &query)
+}
+
+#[tauri::command]
+pub fn clear_search_history(history_state: State<'_, HistoryState>) -> Result<(), String> {
+    let mut history_lock = history_state.history.lock().unwrap();
+    history::clear_history(&mut history_lock)
+}
+
+#[tauri::command]
+pub fn get_incognito_mode(history_state: State<'_, HistoryState>) -> bool {
+    history_state.history.lock().unwrap().incognito
+}
+
+#[tauri::command]
+pub fn set_incognito_mode(history_state: State<'_, HistoryState>, enabled: bool) {
+    logger::info(&format!("Incognito mode: {}", enabled));
+    history_state.history.lock().unwrap().incognito = enabled;
+}
+
+#[tauri::command]
+pub fn get_suggestion_provider_stats(
+    suggestion_state: State<'_, SuggestionState>,
+) -> Vec<ProviderHealth> {
+    let deadline = suggestion_state.config.lock().unwrap().deadline();
+    suggestion_state.stats.snapshot(deadline)
+}
@@ -0,0 +1,53 @@
+use crate::settings::models::Settings;
+use crate::settings::sections::{self, AllSettings, SettingsSection};
+use tauri::AppHandle;
+use tokio::sync::watch;
+
+pub struct SettingsState {
+    settings: watch::Sender<Settings>,
+}
+
+impl SettingsState {
+    pub fn new(settings: Settings) -> Self {
+        Self {
+            settings: watch::channel(settings).0,
+        }
+    }
+
+    pub fn get(&self) -> Settings {
+        self.settings.borrow().clone()
+    }
+
+    /// Receives the settings every time they change, for subsystems that need to react
+    pub fn subscribe(&self) -> watch::Receiver<Settings> {
+        self.settings.subscribe()
+    }
+}
+
+#[tauri::command]
+pub fn get_settings(app_handle: AppHandle) -> AllSettings {
+    sections::all_settings(&app_handle)
+}
+
+/// Changes one group of settings, see [`SettingsSection`]
+#[tauri::command]
+pub fn update_settings(app_handle: AppHandle, change: SettingsSection) -> Result<(), String> {
+    sections::update_section(&app_handle, change)
+}
+
+/// Hands validated settings to every subscriber and the UI, unless nothing changed
+pub fn apply_settings(app_handle: &AppHandle, settings_state: &SettingsState, settings: Settings) {
+    let changed = settings_state.settings.send_if_modified(|current| {
+        if *current == settings {
+            return false;
+        }
+        *current = settings.clone();
+        true
+    });
+
+    if !changed {
+        return;
+    }
+
+    sections::emit_change(app_handle, &SettingsSection::General(settings));
+}
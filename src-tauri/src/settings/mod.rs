@@ -0,0 +1,9 @@
+pub mod api;
+mod models;
+mod sections;
+mod storage;
+
+pub use api::SettingsState;
+pub use models::{SearchEngine, Settings};
+pub use sections::{reload_section, SettingsSection};
+pub use storage::{load_settings, read_settings};
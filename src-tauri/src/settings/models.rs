@@ -0,0 +1,123 @@
+use crate::locale::{self, LocaleSettings};
+use serde::{Deserialize, Serialize};
+
+const MODIFIER_KEYS: &[&str] = &["Ctrl", "Alt", "Shift", "Win"];
+
+/// Engine used for queries without a bang or keyword
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchEngine {
+    #[default]
+    Google,
+    #[serde(rename = "duckduckgo")]
+    DuckDuckGo,
+    Bing,
+    Brave,
+    /// Any search URL, with `{searchTerms}` where the encoded query goes
+    Custom {
+        url: String,
+    },
+}
+
+impl SearchEngine {
+    pub fn search_url(&self, query: &str, locale: &LocaleSettings) -> String {
+        let query = urlencoding::encode(query);
+
+        let (url, params) = match self {
+            SearchEngine::Google => (
+                format!("https://www.google.com/search?q={}", query),
+                locale::google_params(locale),
+            ),
+            SearchEngine::DuckDuckGo => (
+                format!("https://duckduckgo.com/?q={}", query),
+                locale::duckduckgo_params(locale),
+            ),
+            SearchEngine::Bing => (
+                format!("https://www.bing.com/search?q={}", query),
+                locale::bing_params(locale),
+            ),
+            SearchEngine::Brave => (
+                format!("https://search.brave.com/search?q={}", query),
+                locale::brave_params(locale),
+            ),
+            SearchEngine::Custom { url } => (url.replace("{searchTerms}", &query), Vec::new()),
+        };
+
+        locale::append_params(&url, &params)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Key chord that shows the search window, e.g. `Ctrl + Space`
+    pub hotkey: String,
+    pub run_at_startup: bool,
+    pub default_engine: SearchEngine,
+    /// Days before the DuckDuckGo bang list is downloaded again
+    pub bang_cache_ttl_days: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            hotkey: "Ctrl + Space".to_string(),
+            run_at_startup: false,
+            default_engine: SearchEngine::default(),
+            bang_cache_ttl_days: 7,
+        }
+    }
+}
+
+impl Settings {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_hotkey(&self.hotkey)?;
+
+        if !(1..=365).contains(&self.bang_cache_ttl_days) {
+            return Err("Bang cache lifetime must be between 1 and 365 days".to_string());
+        }
+
+        if let SearchEngine::Custom { url } = &self.default_engine {
+            if !url.contains("{searchTerms}") {
+                return Err("Custom search URL must contain {searchTerms}".to_string());
+            }
+            url::Url::parse(&url.replace("{searchTerms}", ""))
+                .map_err(|e| format!("Invalid custom search URL: {}", e))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn bang_cache_ttl(&self) -> chrono::Duration {
+        chrono::Duration::days(self.bang_cache_ttl_days as i64)
+    }
+}
+
+/// Checks the hotkey is written like `Ctrl + Shift + K`: modifiers followed by one key
+fn validate_hotkey(hotkey: &str) -> Result<(), String> {
+    let keys: Vec<&str> = hotkey.split('+').map(str::trim).collect();
+    let Some((key, modifiers)) = keys.split_last() else {
+        return Err("Hotkey can't be empty".to_string());
+    };
+
+    if modifiers.is_empty() {
+        return Err("Hotkey needs at least one modifier key".to_string());
+    }
+
+    if let Some(modifier) = modifiers
+        .iter()
+        .find(|modifier| !MODIFIER_KEYS.contains(modifier))
+    {
+        return Err(format!(
+            "Unknown modifier '{}' in hotkey, expected one of {}",
+            modifier,
+            MODIFIER_KEYS.join(", ")
+        ));
+    }
+
+    if key.is_empty() || MODIFIER_KEYS.contains(key) {
+        return Err(format!("Hotkey '{}' is missing a key", hotkey));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,201 @@
+use crate::bangs::{self, KeywordConfig};
+use crate::detectors::{self, DetectorConfig, DetectorPipeline};
+use crate::history::{self, HistoryConfig};
+use crate::http::{self, NetworkSettings, NetworkSettingsStatus};
+use crate::locale::{self, LocaleSettings};
+use crate::logger::{self, PrivacyLevel};
+use crate::search::{BangState, DetectorState, HistoryState, LocaleState, SuggestionState};
+use crate::settings::api::{apply_settings, SettingsState};
+use crate::settings::models::Settings;
+use crate::settings::storage::save_settings;
+use crate::suggestions::{self, SuggestionConfig};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+/// One group of settings. Each group is stored in its own file by the module that uses it,
+/// but they're all changed through `update_settings` and reported in `settings-changed`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "section", content = "value", rename_all = "snake_case")]
+pub enum SettingsSection {
+    General(Settings),
+    Locale(LocaleSettings),
+    Suggestions(SuggestionConfig),
+    Network(NetworkSettings),
+    History(HistoryConfig),
+    Detectors(DetectorConfig),
+    Keywords(KeywordConfig),
+    LogPrivacy(PrivacyLevel),
+}
+
+/// Every group of settings as currently applied
+#[derive(Debug, Clone, Serialize)]
+pub struct AllSettings {
+    pub general: Settings,
+    pub locale: LocaleSettings,
+    pub suggestions: SuggestionConfig,
+    pub network: NetworkSettingsStatus,
+    pub history: HistoryConfig,
+    pub detectors: DetectorConfig,
+    pub keywords: KeywordConfig,
+    pub log_privacy: PrivacyLevel,
+}
+
+pub fn all_settings(app_handle: &AppHandle) -> AllSettings {
+    AllSettings {
+        general: app_handle.state::<SettingsState>().get(),
+        locale: app_handle
+            .state::<LocaleState>()
+            .settings
+            .lock()
+            .unwrap()
+            .clone(),
+        suggestions: app_handle
+            .state::<SuggestionState>()
+            .config
+            .lock()
+            .unwrap()
+            .clone(),
+        network: http::settings_status(),
+        history: app_handle
+            .state::<HistoryState>()
+            .history
+            .lock()
+            .unwrap()
+            .config
+            .clone(),
+        detectors: app_handle
+            .state::<DetectorState>()
+            .config
+            .lock()
+            .unwrap()
+            .clone(),
+        keywords: app_handle
+            .state::<BangState>()
+            .keywords
+            .lock()
+            .unwrap()
+            .clone(),
+        log_privacy: logger::privacy_level(),
+    }
+}
+
+/// Validates and saves a group of settings, then applies it and tells the UI
+pub fn update_section(app_handle: &AppHandle, section: SettingsSection) -> Result<(), String> {
+    match section.clone() {
+        SettingsSection::General(settings) => {
+            settings.validate()?;
+            save_settings(&settings)?;
+
+            logger::info("Settings updated");
+            // Only announced when something changed, which apply_settings knows
+            apply_settings(app_handle, &app_handle.state::<SettingsState>(), settings);
+            return Ok(());
+        }
+        SettingsSection::Locale(settings) => {
+            settings.validate()?;
+            locale::save_locale_settings(&settings)?;
+
+            logger::info(&format!("Setting locale: {}", settings.tag()));
+            *app_handle.state::<LocaleState>().settings.lock().unwrap() = settings;
+        }
+        SettingsSection::Suggestions(config) => {
+            config.validate()?;
+            suggestions::save_suggestion_config(&config)?;
+            apply_suggestion_config(app_handle, config);
+        }
+        SettingsSection::Network(settings) => {
+            logger::info("Updating network settings");
+            http::update_settings(settings)?;
+        }
+        SettingsSection::History(config) => {
+            let history_state = app_handle.state::<HistoryState>();
+            let mut history_lock = history_state.history.lock().unwrap();
+            history::update_config(&mut history_lock, config)?;
+        }
+        SettingsSection::Detectors(config) => {
+            detectors::save_detector_config(&config)?;
+            apply_detector_config(app_handle, config);
+        }
+        SettingsSection::Keywords(config) => {
+            let bang_state = app_handle.state::<BangState>();
+            let bangs_lock = bang_state.bangs.lock().unwrap();
+            let mut keywords_lock = bang_state.keywords.lock().unwrap();
+            bangs::update_keyword_config(app_handle, &bangs_lock, &mut keywords_lock, config)?;
+        }
+        SettingsSection::LogPrivacy(level) => {
+            logger::info(&format!("Setting log privacy level: {:?}", level));
+            logger::set_privacy_level(level)?;
+        }
+    }
+
+    emit_change(app_handle, &section);
+    Ok(())
+}
+
+/// Applies a group of settings read from its file after it was edited by hand, without
+/// saving it again, and tells the UI
+pub fn reload_section(app_handle: &AppHandle, section: SettingsSection) -> Result<(), String> {
+    match section.clone() {
+        SettingsSection::General(settings) => {
+            settings.validate()?;
+            apply_settings(app_handle, &app_handle.state::<SettingsState>(), settings);
+            return Ok(());
+        }
+        SettingsSection::Locale(settings) => {
+            settings.validate()?;
+            *app_handle.state::<LocaleState>().settings.lock().unwrap() = settings;
+        }
+        SettingsSection::Suggestions(config) => {
+            config.validate()?;
+            apply_suggestion_config(app_handle, config);
+        }
+        SettingsSection::Network(settings) => http::reload_settings(settings)?,
+        SettingsSection::History(config) => {
+            let history_state = app_handle.state::<HistoryState>();
+            let mut history_lock = history_state.history.lock().unwrap();
+            history::reload_config(&mut history_lock, config)?;
+        }
+        SettingsSection::Detectors(config) => apply_detector_config(app_handle, config),
+        SettingsSection::Keywords(config) => {
+            *app_handle.state::<BangState>().keywords.lock().unwrap() = config;
+        }
+        SettingsSection::LogPrivacy(level) => logger::apply_privacy_level(level),
+    }
+
+    emit_change(app_handle, &section);
+    Ok(())
+}
+
+/// Swaps in providers for the new config, connecting to them before they're first used
+fn apply_suggestion_config(app_handle: &AppHandle, config: SuggestionConfig) {
+    let providers = config.build_providers();
+    let locale = app_handle
+        .state::<LocaleState>()
+        .settings
+        .lock()
+        .unwrap()
+        .clone();
+    for provider in providers.clone() {
+        let locale = locale.clone();
+        tauri::async_runtime::spawn(async move {
+            suggestions::warm_up(provider.as_ref(), &locale).await
+        });
+    }
+
+    let suggestion_state = app_handle.state::<SuggestionState>();
+    suggestion_state.cache.clear();
+    *suggestion_state.providers.lock().unwrap() = providers;
+    *suggestion_state.config.lock().unwrap() = config;
+}
+
+fn apply_detector_config(app_handle: &AppHandle, config: DetectorConfig) {
+    let detector_state = app_handle.state::<DetectorState>();
+    *detector_state.pipeline.lock().unwrap() = DetectorPipeline::from_config(&config);
+    *detector_state.config.lock().unwrap() = config;
+}
+
+pub(crate) fn emit_change(app_handle: &AppHandle, section: &SettingsSection) {
+    if let Err(e) = app_handle.emit("settings-changed", section) {
+        logger::error(&format!("Failed to send settings change: {}", e));
+    }
+}
@@ -0,0 +1,48 @@
+use crate::logger;
+use crate::persist::{self, Schema};
+use crate::settings::models::Settings;
+use crate::startup;
+use std::path::PathBuf;
+
+fn get_settings_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("zephyr")
+        .join("settings.json")
+}
+
+/// Version 1 added the versioned envelope
+const SETTINGS_SCHEMA: Schema = Schema::new(&[persist::unchanged]);
+
+pub fn load_settings() -> Settings {
+    match persist::read_validated(&get_settings_path(), &SETTINGS_SCHEMA, Settings::validate) {
+        Ok(Some(settings)) => settings,
+        // Startup was only ever stored in the registry, so that's where it comes from at first
+        Ok(None) => Settings {
+            run_at_startup: startup::is_enabled(),
+            ..Settings::default()
+        },
+        Err(e) => {
+            logger::error(&format!("Failed to load settings: {}", e));
+            Settings::default()
+        }
+    }
+}
+
+/// Reads, migrates and validates the settings file as it is on disk, `None` when
+/// there isn't one yet
+pub fn read_settings() -> Result<Option<Settings>, String> {
+    let settings =
+        persist::read_without_recovery::<Settings>(&get_settings_path(), &SETTINGS_SCHEMA)
+            .map_err(|e| format!("Failed to read settings: {}", e))?;
+
+    if let Some(settings) = &settings {
+        settings.validate()?;
+    }
+
+    Ok(settings)
+}
+
+pub fn save_settings(settings: &Settings) -> Result<(), String> {
+    persist::write(&get_settings_path(), &SETTINGS_SCHEMA, settings)
+}
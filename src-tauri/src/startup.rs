@@ -1,49 +1,36 @@
 use crate::logger;
-use serde::{Deserialize, Serialize};
+use crate::settings::Settings;
 use std::env;
+use tokio::sync::watch;
 use winreg::enums::*;
 use winreg::RegKey;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct StartupStatus {
-    pub enabled: bool,
-}
+const RUN_KEY: &str = "Software\\Microsoft\\Windows\\CurrentVersion\\Run";
 
-#[tauri::command]
-pub fn get_startup_status() -> Result<StartupStatus, String> {
+pub fn is_enabled() -> bool {
     logger::debug("Checking startup status");
 
     let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let run_key = match hkcu.open_subkey("Software\\Microsoft\\Windows\\CurrentVersion\\Run") {
+    let run_key = match hkcu.open_subkey(RUN_KEY) {
         Ok(key) => key,
-        Err(_) => return Ok(StartupStatus { enabled: false }),
+        Err(_) => return false,
     };
 
     let is_enabled = run_key.get_value::<String, _>("Zephyr").is_ok();
 
     logger::debug(&format!("Startup status: {}", is_enabled));
-    Ok(StartupStatus {
-        enabled: is_enabled,
-    })
+    is_enabled
 }
 
-#[tauri::command]
-pub fn toggle_run_at_startup(enable: bool) -> Result<StartupStatus, String> {
-    logger::info(&format!("Toggling run at startup: {}", enable));
+pub fn set_enabled(enable: bool) -> Result<(), String> {
+    logger::info(&format!("Setting run at startup: {}", enable));
 
     let hkcu = RegKey::predef(HKEY_CURRENT_USER);
 
-    let run_key_result = hkcu.open_subkey_with_flags(
-        "Software\\Microsoft\\Windows\\CurrentVersion\\Run",
-        KEY_WRITE,
-    );
-
-    let run_key = match run_key_result {
+    let run_key = match hkcu.open_subkey_with_flags(RUN_KEY, KEY_WRITE) {
         Ok(key) => key,
         Err(_) => {
-            let (key, _) = hkcu
-                .create_subkey("Software\\Microsoft\\Windows\\CurrentVersion\\Run")
-                .map_err(|e| e.to_string())?;
+            let (key, _) = hkcu.create_subkey(RUN_KEY).map_err(|e| e.to_string())?;
             key
         }
     };
@@ -63,5 +50,24 @@ pub fn toggle_run_at_startup(enable: bool) -> Result<StartupStatus, String> {
         let _ = run_key.delete_value("Zephyr");
     }
 
-    Ok(StartupStatus { enabled: enable })
+    Ok(())
+}
+
+/// Keeps the registry in line with the `run_at_startup` setting, starting with the
+/// value loaded at launch in case the entry was removed or the exe moved
+pub fn follow_settings(mut settings: watch::Receiver<Settings>) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let enable = settings.borrow_and_update().run_at_startup;
+            if enable != is_enabled() {
+                if let Err(e) = set_enabled(enable) {
+                    logger::error(&format!("Failed to update run at startup: {}", e));
+                }
+            }
+
+            if settings.changed().await.is_err() {
+                break;
+            }
+        }
+    });
 }
@@ -0,0 +1,72 @@
+use crate::connectivity;
+use crate::http;
+use crate::locale::LocaleSettings;
+use crate::logger;
+use crate::suggestions::providers::SuggestionProvider;
+use std::time::Duration;
+
+/// Suggestions are useless once the user has moved on, so they get a shorter timeout
+const SUGGESTION_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub async fn fetch_suggestions(
+    provider: &dyn SuggestionProvider,
+    query: &str,
+    locale: &LocaleSettings,
+) -> Result<Vec<String>, String> {
+    let url = provider.request_url(query, locale);
+
+    // The shared client keeps connections alive between keystrokes
+    let response = http::client()
+        .get(&url)
+        .timeout(SUGGESTION_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| {
+            if e.is_connect() || e.is_timeout() {
+                connectivity::report_network_error();
+            }
+            format!("Failed to fetch suggestions: {}", e)
+        })?;
+
+    if !response.status().is_success() {
+        return Err(format!("Request failed with status: {}", response.status()));
+    }
+
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response: {}", e))?;
+
+    let suggestions = provider.parse_response(&body)?;
+    logger::info(&format!(
+        "Found {} suggestions from {}",
+        suggestions.len(),
+        provider.id()
+    ));
+
+    Ok(suggestions)
+}
+
+/// Opens a connection to the provider ahead of the first keystroke, so the first
+/// suggestions don't pay for the DNS lookup and TLS handshake
+pub async fn warm_up(provider: &dyn SuggestionProvider, locale: &LocaleSettings) {
+    let Ok(mut url) = url::Url::parse(&provider.request_url("", locale)) else {
+        return;
+    };
+    url.set_path("/");
+    url.set_query(None);
+
+    match http::client()
+        .head(url.as_str())
+        .timeout(SUGGESTION_TIMEOUT)
+        .send()
+        .await
+    {
+        Ok(_) => logger::debug(&format!("Warmed up connection to {}", provider.id())),
+        Err(e) => logger::warn(&format!(
+            "Failed to warm up connection to {}: {}",
+            provider.id(),
+            e
+        )),
+    }
+}
@@ -0,0 +1,303 @@
+use crate::locale::LocaleSettings;
+use crate::logger;
+use crate::suggestions::api::fetch_suggestions;
+use crate::suggestions::providers::SuggestionProvider;
+use crate::suggestions::stats::ProviderStats;
+use lru::LruCache;
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+
+/// Number of responses kept in memory
+const CACHE_CAPACITY: usize = 256;
+
+/// How long a cached response is served before it's fetched again
+const CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+type FetchResult = Result<Vec<String>, String>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    provider: String,
+    locale: LocaleSettings,
+    query: String,
+}
+
+struct CachedResponse {
+    suggestions: Vec<String>,
+    fetched_at: Instant,
+}
+
+/// In-memory cache of remote suggestions that also shares a single request
+/// between callers asking for the same suggestions at the same time
+pub struct SuggestionCache {
+    entries: Mutex<LruCache<CacheKey, CachedResponse>>,
+    in_flight: Mutex<HashMap<CacheKey, Arc<OnceCell<FetchResult>>>>,
+}
+
+impl Default for SuggestionCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SuggestionCache {
+    pub fn new() -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(NonZeroUsize::new(CACHE_CAPACITY).unwrap())),
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the suggestions from the cache, or fetches them from the provider.
+    /// Only actual requests count towards the provider's stats, measured against the deadline.
+    pub async fn get(
+        &self,
+        provider: &dyn SuggestionProvider,
+        query: &str,
+        locale: &LocaleSettings,
+        stats: &ProviderStats,
+        deadline: Duration,
+    ) -> FetchResult {
+        // The key and the request use the same query, so a cached response is always
+        // the one the provider gave for it
+        let query = query.trim();
+        let key = CacheKey {
+            provider: provider.id().to_string(),
+            locale: locale.clone(),
+            query: query.to_string(),
+        };
+
+        if let Some(suggestions) = self.lookup(&key) {
+            logger::debug(&format!("Suggestion cache hit for {}", provider.id()));
+            return Ok(suggestions);
+        }
+
+        let cell = self
+            .in_flight
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_default()
+            .clone();
+        // Drops the shared request once nobody waits for it, even if every caller is cancelled
+        let _in_flight = InFlight {
+            cache: self,
+            key: &key,
+            cell: &cell,
+        };
+
+        // Only the first caller runs the request, the rest wait for its result. If that
+        // caller is cancelled, the next one waiting takes over the request.
+        let result = cell
+            .get_or_init(|| async {
+                let timer = stats.start(provider.id(), deadline);
+                let result = fetch_suggestions(provider, query, locale).await;
+                timer.finish(result.is_ok());
+                result
+            })
+            .await
+            .clone();
+
+        if let Ok(suggestions) = &result {
+            self.entries.lock().unwrap().put(
+                key.clone(),
+                CachedResponse {
+                    suggestions: suggestions.clone(),
+                    fetched_at: Instant::now(),
+                },
+            );
+        }
+
+        result
+    }
+
+    fn lookup(&self, key: &CacheKey) -> Option<Vec<String>> {
+        let mut entries = self.entries.lock().unwrap();
+
+        match entries.get(key) {
+            Some(cached) if cached.fetched_at.elapsed() < CACHE_TTL => {
+                Some(cached.suggestions.clone())
+            }
+            Some(_) => {
+                entries.pop(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+/// A caller's share of a request in `in_flight`
+struct InFlight<'a> {
+    cache: &'a SuggestionCache,
+    key: &'a CacheKey,
+    cell: &'a Arc<OnceCell<FetchResult>>,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        let mut in_flight = self.cache.in_flight.lock().unwrap();
+        let Some(current) = in_flight.get(self.key) else {
+            return;
+        };
+
+        // The map holds one reference and this caller another, anything more is a caller
+        // still waiting. A finished request is cached or failed, so it's dropped straight
+        // away; otherwise it stays for whoever waits to take over.
+        let finished = self.cell.initialized();
+        if Arc::ptr_eq(current, self.cell) && (finished || Arc::strong_count(self.cell) <= 2) {
+            in_flight.remove(self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale::SafeSearch;
+    use crate::suggestions::providers::OpenSearchProvider;
+    use crate::test_server::{self, Route};
+
+    const JSON: &str = "application/json";
+    const DEADLINE: Duration = Duration::from_millis(150);
+
+    fn locale() -> LocaleSettings {
+        LocaleSettings {
+            language: "en".to_string(),
+            region: None,
+            safe_search: SafeSearch::default(),
+        }
+    }
+
+    fn provider(base_url: &str) -> OpenSearchProvider {
+        OpenSearchProvider::new("test", &format!("{}/ac?q={{searchTerms}}", base_url))
+    }
+
+    fn key(query: &str) -> CacheKey {
+        CacheKey {
+            provider: "test".to_string(),
+            locale: locale(),
+            query: query.to_string(),
+        }
+    }
+
+    fn requests(stats: &ProviderStats) -> u32 {
+        stats
+            .snapshot(Duration::from_secs(1))
+            .first()
+            .map_or(0, |health| health.requests)
+    }
+
+    #[test]
+    fn expires_responses_after_the_ttl() {
+        let cache = SuggestionCache::new();
+        cache.entries.lock().unwrap().put(
+            key("fresh"),
+            CachedResponse {
+                suggestions: vec!["fresh".to_string()],
+                fetched_at: Instant::now(),
+            },
+        );
+        cache.entries.lock().unwrap().put(
+            key("stale"),
+            CachedResponse {
+                suggestions: vec!["stale".to_string()],
+                fetched_at: Instant::now() - CACHE_TTL,
+            },
+        );
+
+        assert_eq!(cache.lookup(&key("fresh")), Some(vec!["fresh".to_string()]));
+        assert_eq!(cache.lookup(&key("stale")), None);
+        assert!(!cache.entries.lock().unwrap().contains(&key("stale")));
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_response() {
+        let cache = SuggestionCache::new();
+        for i in 0..CACHE_CAPACITY {
+            cache.entries.lock().unwrap().put(
+                key(&i.to_string()),
+                CachedResponse {
+                    suggestions: Vec::new(),
+                    fetched_at: Instant::now(),
+                },
+            );
+        }
+
+        // Reading the oldest entry makes the second one the least recently used
+        assert!(cache.lookup(&key("0")).is_some());
+        cache.entries.lock().unwrap().put(
+            key("new"),
+            CachedResponse {
+                suggestions: Vec::new(),
+                fetched_at: Instant::now(),
+            },
+        );
+
+        assert!(cache.lookup(&key("0")).is_some());
+        assert!(cache.lookup(&key("1")).is_none());
+        assert!(cache.lookup(&key("new")).is_some());
+    }
+
+    #[tokio::test]
+    async fn shares_one_request_between_concurrent_callers() {
+        let base_url =
+            test_server::serve(vec![Route::new("/ac", JSON, r#"["rust", ["rust book"]]"#)
+                .delayed(Duration::from_millis(200))]);
+        let provider = provider(&base_url);
+        let cache = SuggestionCache::new();
+        let stats = ProviderStats::default();
+        let locale = locale();
+
+        let (first, second) = tokio::join!(
+            cache.get(&provider, "rust", &locale, &stats, DEADLINE),
+            cache.get(&provider, " rust ", &locale, &stats, DEADLINE),
+        );
+
+        assert_eq!(first, Ok(vec!["rust book".to_string()]));
+        assert_eq!(second, first);
+        assert_eq!(requests(&stats), 1);
+        assert!(cache.in_flight.lock().unwrap().is_empty());
+
+        // Served from the cache from now on
+        cache
+            .get(&provider, "rust", &locale, &stats, DEADLINE)
+            .await
+            .unwrap();
+        assert_eq!(requests(&stats), 1);
+    }
+
+    #[tokio::test]
+    async fn fetches_again_after_every_caller_was_cancelled() {
+        let base_url =
+            test_server::serve(vec![Route::new("/ac", JSON, r#"["rust", ["rust book"]]"#)
+                .delayed(Duration::from_millis(200))]);
+        let provider = provider(&base_url);
+        let cache = SuggestionCache::new();
+        let stats = ProviderStats::default();
+
+        let cancelled = tokio::time::timeout(
+            Duration::from_millis(50),
+            cache.get(&provider, "rust", &locale(), &stats, DEADLINE),
+        )
+        .await;
+        assert!(cancelled.is_err());
+        assert!(cache.in_flight.lock().unwrap().is_empty());
+        // Cancelled before the deadline, so it doesn't count against the provider
+        assert_eq!(requests(&stats), 0);
+
+        let retried = cache
+            .get(&provider, "rust", &locale(), &stats, DEADLINE)
+            .await;
+        assert_eq!(retried, Ok(vec!["rust book".to_string()]));
+        assert!(cache.in_flight.lock().unwrap().is_empty());
+        assert_eq!(requests(&stats), 1);
+    }
+}
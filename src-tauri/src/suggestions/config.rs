@@ -0,0 +1,189 @@
+use crate::logger;
+use crate::persist::{self, Schema};
+use crate::suggestions::providers::{OpenSearchProvider, SuggestionProvider};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    #[default]
+    Google,
+    #[serde(rename = "duckduckgo")]
+    DuckDuckGo,
+    Bing,
+    Brave,
+    Wikipedia,
+    /// Any endpoint returning OpenSearch suggestions JSON, with `{searchTerms}` in the URL
+    OpenSearch {
+        url: String,
+    },
+}
+
+impl ProviderKind {
+    pub fn build(&self) -> Arc<dyn SuggestionProvider> {
+        match self {
+            ProviderKind::Google => Arc::new(OpenSearchProvider::google()),
+            ProviderKind::DuckDuckGo => Arc::new(OpenSearchProvider::duckduckgo()),
+            ProviderKind::Bing => Arc::new(OpenSearchProvider::bing()),
+            ProviderKind::Brave => Arc::new(OpenSearchProvider::brave()),
+            ProviderKind::Wikipedia => Arc::new(OpenSearchProvider::wikipedia()),
+            ProviderKind::OpenSearch { url } => {
+                Arc::new(OpenSearchProvider::new("opensearch", url))
+            }
+        }
+    }
+}
+
+/// Longest deadline that can be configured, past it the dropdown would feel unresponsive
+const MAX_DEADLINE_MS: u64 = 2000;
+
+fn default_deadline_ms() -> u64 {
+    150
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuggestionConfig {
+    #[serde(default)]
+    pub provider: ProviderKind,
+    /// Providers queried at the same time as the main one, merged after it
+    #[serde(default)]
+    pub extra_providers: Vec<ProviderKind>,
+    /// How long to wait for remote suggestions before showing what has arrived.
+    /// Anything later is sent to the UI once it comes in.
+    #[serde(default = "default_deadline_ms")]
+    pub deadline_ms: u64,
+}
+
+impl Default for SuggestionConfig {
+    fn default() -> Self {
+        Self {
+            provider: ProviderKind::default(),
+            extra_providers: Vec::new(),
+            deadline_ms: default_deadline_ms(),
+        }
+    }
+}
+
+impl SuggestionConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for provider in std::iter::once(&self.provider).chain(&self.extra_providers) {
+            if let ProviderKind::OpenSearch { url } = provider {
+                if !url.contains("{searchTerms}") {
+                    return Err("OpenSearch suggestion URL must contain {searchTerms}".to_string());
+                }
+                url::Url::parse(&url.replace("{searchTerms}", ""))
+                    .map_err(|e| format!("Invalid OpenSearch suggestion URL: {}", e))?;
+            }
+        }
+
+        if self.deadline_ms == 0 || self.deadline_ms > MAX_DEADLINE_MS {
+            return Err(format!(
+                "Suggestion deadline must be between 1 and {} ms",
+                MAX_DEADLINE_MS
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// The main provider followed by the extra ones
+    pub fn build_providers(&self) -> Vec<Arc<dyn SuggestionProvider>> {
+        std::iter::once(&self.provider)
+            .chain(&self.extra_providers)
+            .map(ProviderKind::build)
+            .collect()
+    }
+
+    pub fn deadline(&self) -> Duration {
+        Duration::from_millis(self.deadline_ms)
+    }
+}
+
+/// Version 1 added the versioned envelope
+const CONFIG_SCHEMA: Schema = Schema::new(&[persist::unchanged]);
+
+pub(crate) fn get_config_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("zephyr")
+        .join("suggestions.json")
+}
+
+pub fn load_suggestion_config() -> SuggestionConfig {
+    match persist::read(&get_config_path(), &CONFIG_SCHEMA) {
+        Ok(config) => config.unwrap_or_default(),
+        Err(e) => {
+            logger::error(&format!("Failed to load suggestion config: {}", e));
+            SuggestionConfig::default()
+        }
+    }
+}
+
+/// Reads the config after it was edited by hand, reporting errors instead of recovering a backup
+pub fn read_suggestion_config() -> Result<Option<SuggestionConfig>, String> {
+    persist::read_without_recovery(&get_config_path(), &CONFIG_SCHEMA)
+}
+
+pub fn save_suggestion_config(config: &SuggestionConfig) -> Result<(), String> {
+    persist::write(&get_config_path(), &CONFIG_SCHEMA, config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_the_main_provider_first() {
+        let config = SuggestionConfig {
+            provider: ProviderKind::Brave,
+            extra_providers: vec![
+                ProviderKind::Google,
+                ProviderKind::DuckDuckGo,
+                ProviderKind::Bing,
+                ProviderKind::Wikipedia,
+                ProviderKind::OpenSearch {
+                    url: "https://example.com/ac?q={searchTerms}".to_string(),
+                },
+            ],
+            ..Default::default()
+        };
+
+        let ids: Vec<String> = config
+            .build_providers()
+            .iter()
+            .map(|provider| provider.id().to_string())
+            .collect();
+
+        assert_eq!(
+            ids,
+            [
+                "brave",
+                "google",
+                "duckduckgo",
+                "bing",
+                "wikipedia",
+                "opensearch"
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_provider_kinds_from_settings() {
+        let config: SuggestionConfig = serde_json::from_str(
+            r#"{"provider": "duckduckgo", "extra_providers": [{"open_search": {"url": "https://example.com/ac?q={searchTerms}"}}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.provider, ProviderKind::DuckDuckGo);
+        assert_eq!(
+            config.extra_providers,
+            [ProviderKind::OpenSearch {
+                url: "https://example.com/ac?q={searchTerms}".to_string()
+            }]
+        );
+        assert!(config.validate().is_ok());
+    }
+}
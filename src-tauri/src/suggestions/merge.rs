@@ -0,0 +1,15 @@
+use crate::suggestions::models::Suggestion;
+use std::collections::HashSet;
+
+/// Merges suggestions from every source by score, keeping the best-ranked of any duplicates.
+/// Ties keep the order the sources were given in, so the ranking is stable.
+pub fn merge_suggestions(sources: Vec<Vec<Suggestion>>, limit: usize) -> Vec<Suggestion> {
+    let mut all: Vec<Suggestion> = sources.into_iter().flatten().collect();
+    all.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    let mut seen = HashSet::new();
+    all.into_iter()
+        .filter(|suggestion| seen.insert(suggestion.completion.trim().to_lowercase()))
+        .take(limit)
+        .collect()
+}
@@ -0,0 +1,21 @@
+mod api;
+mod cache;
+mod config;
+mod merge;
+mod models;
+mod providers;
+mod race;
+mod registry;
+mod stats;
+
+pub use api::warm_up;
+pub use cache::SuggestionCache;
+pub use config::{
+    load_suggestion_config, read_suggestion_config, save_suggestion_config, SuggestionConfig,
+};
+pub use merge::merge_suggestions;
+pub use models::{Suggestion, SuggestionUpdate};
+pub use providers::SuggestionProvider;
+pub use race::ProviderRace;
+pub use registry::provider_for_bang;
+pub use stats::{ProviderHealth, ProviderStats};
@@ -0,0 +1,130 @@
+use crate::history::HistoryEntry;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionKind {
+    /// Recognized by a routing rule or detector, e.g. an email address or CVE
+    Detected,
+    Url,
+    Path,
+    Bang,
+    History,
+    Remote,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Suggestion {
+    pub kind: SuggestionKind,
+    /// Text shown in the suggestions list
+    pub display: String,
+    /// Text placed in the search bar when the suggestion is picked
+    pub completion: String,
+    /// Where the suggestion leads, when known ahead of time
+    pub url: Option<String>,
+    /// Name of the icon the UI should show
+    pub icon: String,
+    /// Ranking score, higher comes first
+    pub score: f64,
+}
+
+impl Suggestion {
+    pub fn detected(query: &str, source: &str, url: String) -> Self {
+        Self {
+            kind: SuggestionKind::Detected,
+            display: format!("{} ({})", query, url),
+            completion: query.to_string(),
+            url: Some(url),
+            icon: source.to_string(),
+            score: 1.0,
+        }
+    }
+
+    pub fn url(query: &str, url: String) -> Self {
+        Self {
+            kind: SuggestionKind::Url,
+            display: url.clone(),
+            completion: query.to_string(),
+            url: Some(url),
+            icon: "link".to_string(),
+            score: 1.0,
+        }
+    }
+
+    pub fn path(query: &str, path: String, is_dir: bool) -> Self {
+        Self {
+            kind: SuggestionKind::Path,
+            display: path.clone(),
+            completion: query.to_string(),
+            url: Some(path),
+            icon: if is_dir { "folder" } else { "file" }.to_string(),
+            score: 1.0,
+        }
+    }
+
+    /// `completion` is the query with the bang being typed filled in
+    pub fn bang(completion: String, bang_id: &str, name: &str, rank: usize) -> Self {
+        Self {
+            kind: SuggestionKind::Bang,
+            display: format!("!{} ({})", bang_id, name),
+            completion,
+            url: None,
+            icon: "bang".to_string(),
+            score: 0.8 - 0.02 * rank as f64,
+        }
+    }
+
+    /// History is ranked by frecency relative to the best match
+    pub fn history(entry: &HistoryEntry, max_frecency: f64) -> Self {
+        let relative_frecency = if max_frecency > 0.0 {
+            entry.frecency / max_frecency
+        } else {
+            0.0
+        };
+
+        Self {
+            kind: SuggestionKind::History,
+            display: entry.query.clone(),
+            completion: entry.query.clone(),
+            url: Some(entry.url.clone()),
+            icon: "history".to_string(),
+            score: 0.6 + 0.3 * relative_frecency,
+        }
+    }
+
+    /// Remote suggestions keep the provider's order. Scores stop at 0, the merge keeps
+    /// the order of equal scores.
+    pub fn remote(text: String, rank: usize) -> Self {
+        Self {
+            kind: SuggestionKind::Remote,
+            display: text.clone(),
+            completion: text,
+            url: None,
+            icon: "search".to_string(),
+            score: (0.7 - 0.05 * rank as f64).max(0.0),
+        }
+    }
+}
+
+/// Suggestions sent to the UI once the providers that missed the deadline have answered
+#[derive(Debug, Clone, Serialize)]
+pub struct SuggestionUpdate {
+    /// Sequence number of the request the suggestions belong to
+    pub seq: u64,
+    pub suggestions: Vec<Suggestion>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remote_scores_stay_positive_and_in_provider_order() {
+        let scores: Vec<f64> = (0..30)
+            .map(|rank| Suggestion::remote(rank.to_string(), rank).score)
+            .collect();
+
+        assert!(scores.iter().all(|score| (0.0..=0.7).contains(score)));
+        assert!(scores.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+}
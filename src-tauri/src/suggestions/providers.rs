@@ -0,0 +1,369 @@
+use crate::locale::{self, LocaleSettings};
+
+/// A source of search suggestions
+pub trait SuggestionProvider: Send + Sync {
+    /// Identifier used in logs and settings
+    fn id(&self) -> &str;
+
+    /// URL to request suggestions for the query from, in the user's language and region
+    fn request_url(&self, query: &str, locale: &LocaleSettings) -> String;
+
+    /// Extracts the suggestions from the response body
+    fn parse_response(&self, body: &str) -> Result<Vec<String>, String>;
+}
+
+/// Provider for endpoints returning the OpenSearch suggestions format:
+/// `["query", ["suggestion 1", "suggestion 2", ...], ...]`
+pub struct OpenSearchProvider {
+    id: String,
+    url_template: String,
+    /// Maps the locale settings to the endpoint's query parameters
+    locale_params: fn(&LocaleSettings) -> Vec<(&'static str, String)>,
+}
+
+impl OpenSearchProvider {
+    /// `url_template` contains `{searchTerms}` where the encoded query goes, and may
+    /// contain `{language}` for the user's language code
+    pub fn new(id: &str, url_template: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            url_template: url_template.to_string(),
+            locale_params: |_| Vec::new(),
+        }
+    }
+
+    fn with_locale_params(
+        mut self,
+        locale_params: fn(&LocaleSettings) -> Vec<(&'static str, String)>,
+    ) -> Self {
+        self.locale_params = locale_params;
+        self
+    }
+
+    pub fn google() -> Self {
+        // Client needs to be specified of Google gets angry
+        Self::new(
+            "google",
+            "https://suggestqueries.google.com/complete/search?client=chrome&q={searchTerms}",
+        )
+        .with_locale_params(locale::google_params)
+    }
+
+    pub fn duckduckgo() -> Self {
+        Self::new(
+            "duckduckgo",
+            "https://duckduckgo.com/ac/?type=list&q={searchTerms}",
+        )
+        .with_locale_params(locale::duckduckgo_params)
+    }
+
+    pub fn bing() -> Self {
+        Self::new(
+            "bing",
+            "https://api.bing.com/osjson.aspx?query={searchTerms}",
+        )
+        .with_locale_params(locale::bing_params)
+    }
+
+    pub fn brave() -> Self {
+        Self::new(
+            "brave",
+            "https://search.brave.com/api/suggest?q={searchTerms}",
+        )
+        .with_locale_params(locale::brave_params)
+    }
+
+    pub fn youtube() -> Self {
+        Self::new(
+            "youtube",
+            "https://suggestqueries.google.com/complete/search?client=firefox&ds=yt&q={searchTerms}",
+        )
+        .with_locale_params(locale::google_params)
+    }
+
+    pub fn wikipedia() -> Self {
+        Self::new(
+            "wikipedia",
+            "https://{language}.wikipedia.org/w/api.php?action=opensearch&format=json&limit=10&search={searchTerms}",
+        )
+    }
+}
+
+impl SuggestionProvider for OpenSearchProvider {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn request_url(&self, query: &str, locale: &LocaleSettings) -> String {
+        let url = self
+            .url_template
+            .replace("{searchTerms}", &urlencoding::encode(query))
+            .replace("{language}", &locale.language);
+
+        locale::append_params(&url, &(self.locale_params)(locale))
+    }
+
+    fn parse_response(&self, body: &str) -> Result<Vec<String>, String> {
+        let data: serde_json::Value =
+            serde_json::from_str(body).map_err(|e| format!("Failed to parse response: {}", e))?;
+
+        // The second element of the array contains the suggestions
+        let suggestions = data
+            .get(1)
+            .and_then(|v| v.as_array())
+            .map(|suggestions| {
+                suggestions
+                    .iter()
+                    .filter_map(|s| s.as_str().map(|s| s.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(suggestions)
+    }
+}
+
+/// Package name suggestions from the npm registry search API
+pub struct NpmProvider {
+    url_template: String,
+}
+
+impl Default for NpmProvider {
+    fn default() -> Self {
+        Self::new("https://registry.npmjs.org/-/v1/search?size=10&text={searchTerms}")
+    }
+}
+
+impl NpmProvider {
+    /// `url_template` contains `{searchTerms}` where the encoded query goes, e.g. for a
+    /// private registry
+    pub fn new(url_template: &str) -> Self {
+        Self {
+            url_template: url_template.to_string(),
+        }
+    }
+}
+
+impl SuggestionProvider for NpmProvider {
+    fn id(&self) -> &str {
+        "npm"
+    }
+
+    fn request_url(&self, query: &str, _locale: &LocaleSettings) -> String {
+        self.url_template
+            .replace("{searchTerms}", &urlencoding::encode(query))
+    }
+
+    fn parse_response(&self, body: &str) -> Result<Vec<String>, String> {
+        let data: serde_json::Value =
+            serde_json::from_str(body).map_err(|e| format!("Failed to parse response: {}", e))?;
+
+        let names = data
+            .get("objects")
+            .and_then(|v| v.as_array())
+            .map(|objects| {
+                objects
+                    .iter()
+                    .filter_map(|o| o.pointer("/package/name").and_then(|n| n.as_str()))
+                    .map(|name| name.to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(names)
+    }
+}
+
+/// Crate name suggestions from the crates.io API, also used for docs.rs
+pub struct CratesIoProvider {
+    url_template: String,
+}
+
+impl Default for CratesIoProvider {
+    fn default() -> Self {
+        Self::new("https://crates.io/api/v1/crates?per_page=10&q={searchTerms}")
+    }
+}
+
+impl CratesIoProvider {
+    /// `url_template` contains `{searchTerms}` where the encoded query goes, e.g. for an
+    /// alternative registry
+    pub fn new(url_template: &str) -> Self {
+        Self {
+            url_template: url_template.to_string(),
+        }
+    }
+}
+
+impl SuggestionProvider for CratesIoProvider {
+    fn id(&self) -> &str {
+        "crates.io"
+    }
+
+    fn request_url(&self, query: &str, _locale: &LocaleSettings) -> String {
+        self.url_template
+            .replace("{searchTerms}", &urlencoding::encode(query))
+    }
+
+    fn parse_response(&self, body: &str) -> Result<Vec<String>, String> {
+        let data: serde_json::Value =
+            serde_json::from_str(body).map_err(|e| format!("Failed to parse response: {}", e))?;
+
+        let names = data
+            .get("crates")
+            .and_then(|v| v.as_array())
+            .map(|crates| {
+                crates
+                    .iter()
+                    .filter_map(|c| c.get("name").and_then(|n| n.as_str()))
+                    .map(|name| name.to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(names)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale::SafeSearch;
+
+    fn locale(language: &str, region: Option<&str>, safe_search: SafeSearch) -> LocaleSettings {
+        LocaleSettings {
+            language: language.to_string(),
+            region: region.map(str::to_string),
+            safe_search,
+        }
+    }
+
+    #[test]
+    fn builds_request_urls_for_the_locale() {
+        let us = locale("en", Some("US"), SafeSearch::Moderate);
+        let brazil_unfiltered = locale("pt", Some("BR"), SafeSearch::Off);
+        let german_strict = locale("de", None, SafeSearch::Strict);
+
+        let cases = [
+            (
+                OpenSearchProvider::google(),
+                &us,
+                "https://suggestqueries.google.com/complete/search?client=chrome&q=rust%20lang&hl=en&gl=US",
+            ),
+            (
+                OpenSearchProvider::google(),
+                &brazil_unfiltered,
+                "https://suggestqueries.google.com/complete/search?client=chrome&q=rust%20lang&hl=pt&gl=BR&safe=off",
+            ),
+            (
+                OpenSearchProvider::duckduckgo(),
+                &us,
+                "https://duckduckgo.com/ac/?type=list&q=rust%20lang&kl=us-en",
+            ),
+            (
+                OpenSearchProvider::duckduckgo(),
+                &german_strict,
+                "https://duckduckgo.com/ac/?type=list&q=rust%20lang&kp=1",
+            ),
+            (
+                OpenSearchProvider::bing(),
+                &brazil_unfiltered,
+                "https://api.bing.com/osjson.aspx?query=rust%20lang&setlang=pt&mkt=pt-BR&cc=BR&adlt=off",
+            ),
+            (
+                OpenSearchProvider::brave(),
+                &us,
+                "https://search.brave.com/api/suggest?q=rust%20lang&country=us",
+            ),
+            (
+                OpenSearchProvider::brave(),
+                &german_strict,
+                "https://search.brave.com/api/suggest?q=rust%20lang&safesearch=strict",
+            ),
+            (
+                OpenSearchProvider::wikipedia(),
+                &german_strict,
+                "https://de.wikipedia.org/w/api.php?action=opensearch&format=json&limit=10&search=rust%20lang",
+            ),
+        ];
+
+        for (provider, locale, expected) in cases {
+            assert_eq!(
+                provider.request_url("rust lang", locale),
+                expected,
+                "{}",
+                provider.id()
+            );
+        }
+    }
+
+    #[test]
+    fn parses_captured_responses() {
+        let cases = [
+            (
+                OpenSearchProvider::google(),
+                r#"["rust",["rust","rust lang","rust game"],["","",""],[],{"google:clientdata":{"bpc":false,"tlw":false},"google:suggesttype":["QUERY","QUERY","QUERY"],"google:verbatimrelevance":1300}]"#,
+                vec!["rust", "rust lang", "rust game"],
+            ),
+            (
+                OpenSearchProvider::duckduckgo(),
+                r#"["rust",["rust","rust lang","rust game"]]"#,
+                vec!["rust", "rust lang", "rust game"],
+            ),
+            (
+                OpenSearchProvider::bing(),
+                r#"["rust",["rust","rust lang","rust game","rustoleum"]]"#,
+                vec!["rust", "rust lang", "rust game", "rustoleum"],
+            ),
+            (
+                OpenSearchProvider::brave(),
+                r#"["rust",["rust lang","rust game","rust programming"]]"#,
+                vec!["rust lang", "rust game", "rust programming"],
+            ),
+            (
+                OpenSearchProvider::wikipedia(),
+                r#"["rust",["Rust","Rust (programming language)"],["",""],["https://en.wikipedia.org/wiki/Rust","https://en.wikipedia.org/wiki/Rust_(programming_language)"]]"#,
+                vec!["Rust", "Rust (programming language)"],
+            ),
+        ];
+
+        for (provider, body, expected) in cases {
+            assert_eq!(
+                provider.parse_response(body),
+                Ok(expected.iter().map(|s| s.to_string()).collect()),
+                "{}",
+                provider.id()
+            );
+        }
+    }
+
+    #[test]
+    fn parses_package_registry_responses() {
+        let npm = r#"{"objects":[{"package":{"name":"rust-wasm","version":"1.0.0"}},{"package":{"name":"rustc"}}],"total":2}"#;
+        let crates = r#"{"crates":[{"name":"rustls","max_version":"0.23.0"},{"name":"rustix"}],"meta":{"total":2}}"#;
+
+        assert_eq!(
+            NpmProvider::default().parse_response(npm).unwrap(),
+            ["rust-wasm", "rustc"]
+        );
+        assert_eq!(
+            CratesIoProvider::default().parse_response(crates).unwrap(),
+            ["rustls", "rustix"]
+        );
+    }
+
+    #[test]
+    fn rejects_bodies_that_are_not_json() {
+        let provider = OpenSearchProvider::google();
+
+        assert!(provider
+            .parse_response("<html>Too many requests</html>")
+            .is_err());
+        // Valid JSON of another shape just has no suggestions
+        assert_eq!(
+            provider.parse_response(r#"{"error":"quota"}"#),
+            Ok(Vec::new())
+        );
+        assert_eq!(provider.parse_response(r#"["rust"]"#), Ok(Vec::new()));
+    }
+}
@@ -0,0 +1,209 @@
+use crate::locale::LocaleSettings;
+use crate::logger;
+use crate::suggestions::cache::SuggestionCache;
+use crate::suggestions::providers::SuggestionProvider;
+use crate::suggestions::stats::ProviderStats;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+
+/// Suggestion requests to several providers running at the same time.
+/// Dropping the race cancels the requests that haven't finished.
+pub struct ProviderRace {
+    tasks: JoinSet<(usize, Result<Vec<String>, String>)>,
+    /// Suggestions of each provider, in the order the providers were given
+    results: Vec<Option<Vec<String>>>,
+}
+
+impl ProviderRace {
+    pub fn start(
+        providers: Vec<Arc<dyn SuggestionProvider>>,
+        query: &str,
+        locale: &LocaleSettings,
+        cache: Arc<SuggestionCache>,
+        stats: Arc<ProviderStats>,
+        deadline: Duration,
+    ) -> Self {
+        let mut tasks = JoinSet::new();
+        let results = vec![None; providers.len()];
+
+        for (index, provider) in providers.into_iter().enumerate() {
+            let query = query.to_string();
+            let locale = locale.clone();
+            let cache = cache.clone();
+            let stats = stats.clone();
+
+            tasks.spawn(async move {
+                let result = cache
+                    .get(provider.as_ref(), &query, &locale, &stats, deadline)
+                    .await;
+                if let Err(e) = &result {
+                    logger::warn(&format!("{}: {}", provider.id(), e));
+                }
+                (index, result)
+            });
+        }
+
+        Self { tasks, results }
+    }
+
+    /// Collects results until every provider has answered or the deadline passes
+    pub async fn collect_until(&mut self, deadline: Duration) {
+        let deadline = Instant::now() + deadline;
+        while !self.is_finished() {
+            match tokio::time::timeout_at(deadline, self.tasks.join_next()).await {
+                Ok(Some(joined)) => self.add_result(joined),
+                Ok(None) | Err(_) => break,
+            }
+        }
+    }
+
+    /// Collects results until every provider has answered
+    pub async fn collect_all(&mut self) {
+        while let Some(joined) = self.tasks.join_next().await {
+            self.add_result(joined);
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Suggestions received so far, deduplicated and in provider order
+    pub fn suggestions(&self) -> Vec<String> {
+        let mut seen = HashSet::new();
+        self.results
+            .iter()
+            .flatten()
+            .flatten()
+            .filter(|suggestion| seen.insert(suggestion.trim().to_lowercase()))
+            .cloned()
+            .collect()
+    }
+
+    fn add_result(
+        &mut self,
+        joined: Result<(usize, Result<Vec<String>, String>), tokio::task::JoinError>,
+    ) {
+        match joined {
+            Ok((index, result)) => self.results[index] = Some(result.unwrap_or_default()),
+            Err(e) => logger::error(&format!("Suggestion request task failed: {}", e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::locale::SafeSearch;
+    use crate::suggestions::providers::{CratesIoProvider, NpmProvider, OpenSearchProvider};
+    use crate::test_server::{self, Route};
+
+    const JSON: &str = "application/json";
+
+    fn locale() -> LocaleSettings {
+        LocaleSettings {
+            language: "en".to_string(),
+            region: None,
+            safe_search: SafeSearch::default(),
+        }
+    }
+
+    fn start(providers: Vec<Arc<dyn SuggestionProvider>>) -> (ProviderRace, Arc<ProviderStats>) {
+        let stats = Arc::new(ProviderStats::default());
+        let race = ProviderRace::start(
+            providers,
+            "rust",
+            &locale(),
+            Arc::new(SuggestionCache::new()),
+            stats.clone(),
+            Duration::from_millis(300),
+        );
+        (race, stats)
+    }
+
+    #[tokio::test]
+    async fn keeps_provider_order_whatever_answers_first() {
+        let base_url = test_server::serve(vec![
+            Route::new("/slow", JSON, r#"["rust", ["rust book", "Rust Lang"]]"#)
+                .delayed(Duration::from_millis(300)),
+            Route::new("/fast", JSON, r#"["rust", ["rust lang", "rustup"]]"#),
+        ]);
+        let (mut race, _) = start(vec![
+            Arc::new(OpenSearchProvider::new(
+                "slow",
+                &format!("{}/slow?q={{searchTerms}}", base_url),
+            )),
+            Arc::new(OpenSearchProvider::new(
+                "fast",
+                &format!("{}/fast?q={{searchTerms}}", base_url),
+            )),
+        ]);
+
+        race.collect_all().await;
+
+        // Duplicates keep the spelling of the earlier provider
+        assert_eq!(race.suggestions(), ["rust book", "Rust Lang", "rustup"]);
+    }
+
+    #[tokio::test]
+    async fn leaves_providers_past_the_deadline_for_later() {
+        let base_url = test_server::serve(vec![
+            Route::new("/slow", JSON, r#"["rust", ["rust book"]]"#)
+                .delayed(Duration::from_millis(1000)),
+            Route::new("/fast", JSON, r#"["rust", ["rustup"]]"#),
+        ]);
+        let (mut race, _) = start(vec![
+            Arc::new(OpenSearchProvider::new(
+                "slow",
+                &format!("{}/slow?q={{searchTerms}}", base_url),
+            )),
+            Arc::new(OpenSearchProvider::new(
+                "fast",
+                &format!("{}/fast?q={{searchTerms}}", base_url),
+            )),
+        ]);
+
+        race.collect_until(Duration::from_millis(300)).await;
+        assert!(!race.is_finished());
+        assert_eq!(race.suggestions(), ["rustup"]);
+
+        race.collect_all().await;
+        assert!(race.is_finished());
+        assert_eq!(race.suggestions(), ["rust book", "rustup"]);
+    }
+
+    #[tokio::test]
+    async fn skips_malformed_responses() {
+        let base_url = test_server::serve(vec![
+            Route::new("/npm", JSON, "<html>Service unavailable</html>"),
+            Route::new(
+                "/crates",
+                JSON,
+                r#"{"crates": [{"name": "rust-embed"}, {"name": "rustls"}]}"#,
+            ),
+        ]);
+        let (mut race, stats) = start(vec![
+            Arc::new(NpmProvider::new(&format!(
+                "{}/npm?text={{searchTerms}}",
+                base_url
+            ))),
+            Arc::new(CratesIoProvider::new(&format!(
+                "{}/crates?q={{searchTerms}}",
+                base_url
+            ))),
+        ]);
+
+        race.collect_all().await;
+
+        assert_eq!(race.suggestions(), ["rust-embed", "rustls"]);
+        let health = stats.snapshot(Duration::from_secs(1));
+        assert_eq!(health.len(), 2);
+        assert_eq!(health[0].provider, "crates.io");
+        assert_eq!(health[0].errors, 0);
+        assert_eq!(health[1].provider, "npm");
+        assert_eq!(health[1].errors, 1);
+    }
+}
@@ -0,0 +1,77 @@
+use crate::bangs::Bang;
+use crate::suggestions::providers::{
+    CratesIoProvider, NpmProvider, OpenSearchProvider, SuggestionProvider,
+};
+use std::sync::Arc;
+
+/// Returns the provider for suggestions while the bang is in use, preferring the
+/// suggest URL declared by a custom bang over the built-in providers
+pub fn provider_for_bang(bang: &Bang) -> Option<Arc<dyn SuggestionProvider>> {
+    if let Some(suggest_url) = &bang.suggest_url {
+        return Some(Arc::new(OpenSearchProvider::new(&bang.id, suggest_url)));
+    }
+
+    let provider: Arc<dyn SuggestionProvider> = match bang.id.as_str() {
+        "w" | "wiki" | "wikipedia" => Arc::new(OpenSearchProvider::wikipedia()),
+        "yt" | "youtube" => Arc::new(OpenSearchProvider::youtube()),
+        "npm" | "npmjs" => Arc::new(NpmProvider::default()),
+        "crates" | "crate" | "cratesio" | "docs.rs" | "docsrs" => {
+            Arc::new(CratesIoProvider::default())
+        }
+        _ => return None,
+    };
+
+    Some(provider)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bang(id: &str, suggest_url: Option<&str>) -> Bang {
+        Bang {
+            id: id.to_string(),
+            name: id.to_string(),
+            search_url: "https://example.com/search?q={{{s}}}".to_string(),
+            home_url: "https://example.com".to_string(),
+            category: String::new(),
+            is_custom: suggest_url.is_some(),
+            suggest_url: suggest_url.map(str::to_string),
+            icon_url: None,
+        }
+    }
+
+    fn provider_id(bang: &Bang) -> Option<String> {
+        provider_for_bang(bang).map(|provider| provider.id().to_string())
+    }
+
+    #[test]
+    fn picks_the_built_in_provider_for_known_bangs() {
+        let cases = [
+            ("w", Some("wikipedia")),
+            ("wikipedia", Some("wikipedia")),
+            ("yt", Some("youtube")),
+            ("npm", Some("npm")),
+            ("crates", Some("crates.io")),
+            ("docs.rs", Some("crates.io")),
+            ("g", None),
+        ];
+
+        for (id, expected) in cases {
+            assert_eq!(provider_id(&bang(id, None)).as_deref(), expected, "!{}", id);
+        }
+    }
+
+    #[test]
+    fn prefers_the_suggest_url_of_the_bang() {
+        let bang = bang("w", Some("https://wiki.example/suggest?q={searchTerms}"));
+
+        let provider = provider_for_bang(&bang).unwrap();
+
+        assert_eq!(provider.id(), "w");
+        assert_eq!(
+            provider.request_url("rust", &crate::locale::LocaleSettings::default()),
+            "https://wiki.example/suggest?q=rust"
+        );
+    }
+}
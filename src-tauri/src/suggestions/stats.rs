@@ -0,0 +1,270 @@
+use crate::suggestions::providers::SuggestionProvider;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Requests needed before a provider can be demoted, so one slow request doesn't count
+const MIN_SAMPLES: u32 = 5;
+
+/// Weight of the newest request in the moving averages
+const SMOOTHING: f64 = 0.2;
+
+/// Error rate above which a provider is demoted
+const MAX_ERROR_RATE: f64 = 0.5;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProviderHealth {
+    pub provider: String,
+    pub requests: u32,
+    pub errors: u32,
+    /// Moving average of the response time
+    pub latency_ms: f64,
+    /// Moving average of the share of failed requests
+    pub error_rate: f64,
+    pub demoted: bool,
+}
+
+impl ProviderHealth {
+    fn record(&mut self, latency: Duration, success: bool) {
+        let latency_ms = latency.as_secs_f64() * 1000.0;
+        let failed = if success { 0.0 } else { 1.0 };
+
+        if self.requests == 0 {
+            self.latency_ms = latency_ms;
+            self.error_rate = failed;
+        } else {
+            self.latency_ms += SMOOTHING * (latency_ms - self.latency_ms);
+            self.error_rate += SMOOTHING * (failed - self.error_rate);
+        }
+
+        self.requests += 1;
+        if !success {
+            self.errors += 1;
+        }
+    }
+
+    fn is_demoted(&self, deadline: Duration) -> bool {
+        self.requests >= MIN_SAMPLES
+            && (self.latency_ms > deadline.as_secs_f64() * 1000.0
+                || self.error_rate > MAX_ERROR_RATE)
+    }
+}
+
+/// Latency and error stats of the suggestion providers for this session
+#[derive(Default)]
+pub struct ProviderStats {
+    providers: Mutex<HashMap<String, ProviderHealth>>,
+}
+
+impl ProviderStats {
+    pub fn record(&self, provider: &str, latency: Duration, success: bool) {
+        self.providers
+            .lock()
+            .unwrap()
+            .entry(provider.to_string())
+            .or_insert_with(|| ProviderHealth {
+                provider: provider.to_string(),
+                ..Default::default()
+            })
+            .record(latency, success);
+    }
+
+    /// Starts timing a request to the provider
+    pub fn start<'a>(&'a self, provider: &'a str, deadline: Duration) -> RequestTimer<'a> {
+        RequestTimer {
+            stats: self,
+            provider,
+            deadline,
+            started: Instant::now(),
+            finished: false,
+        }
+    }
+
+    /// Whether the provider has been consistently slower than the deadline or failing
+    pub fn is_demoted(&self, provider: &str, deadline: Duration) -> bool {
+        self.providers
+            .lock()
+            .unwrap()
+            .get(provider)
+            .is_some_and(|health| health.is_demoted(deadline))
+    }
+
+    /// Orders the providers so the demoted ones come last. Demoted providers are still
+    /// queried, so their answers are merged after the others' and their stats can recover.
+    pub fn rank(
+        &self,
+        providers: Vec<Arc<dyn SuggestionProvider>>,
+        deadline: Duration,
+    ) -> Vec<Arc<dyn SuggestionProvider>> {
+        let (healthy, demoted): (Vec<_>, Vec<_>) = providers
+            .into_iter()
+            .partition(|provider| !self.is_demoted(provider.id(), deadline));
+
+        healthy.into_iter().chain(demoted).collect()
+    }
+
+    pub fn snapshot(&self, deadline: Duration) -> Vec<ProviderHealth> {
+        let mut providers: Vec<ProviderHealth> = self
+            .providers
+            .lock()
+            .unwrap()
+            .values()
+            .map(|health| ProviderHealth {
+                demoted: health.is_demoted(deadline),
+                ..health.clone()
+            })
+            .collect();
+
+        providers.sort_by(|a, b| a.provider.cmp(&b.provider));
+        providers
+    }
+}
+
+/// A request to a provider being timed. A request cancelled once the deadline has passed
+/// counts as timed out, one cancelled earlier because the user moved on isn't recorded.
+pub struct RequestTimer<'a> {
+    stats: &'a ProviderStats,
+    provider: &'a str,
+    deadline: Duration,
+    started: Instant,
+    finished: bool,
+}
+
+impl RequestTimer<'_> {
+    pub fn finish(mut self, success: bool) {
+        self.finished = true;
+        self.stats
+            .record(self.provider, self.started.elapsed(), success);
+    }
+}
+
+impl Drop for RequestTimer<'_> {
+    fn drop(&mut self) {
+        let elapsed = self.started.elapsed();
+        if !self.finished && elapsed >= self.deadline {
+            self.stats.record(self.provider, elapsed, false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::suggestions::providers::OpenSearchProvider;
+
+    const DEADLINE: Duration = Duration::from_millis(150);
+
+    fn health(stats: &ProviderStats, provider: &str) -> ProviderHealth {
+        stats
+            .snapshot(DEADLINE)
+            .into_iter()
+            .find(|health| health.provider == provider)
+            .unwrap()
+    }
+
+    fn record_times(stats: &ProviderStats, times: u32, latency_ms: u64, success: bool) {
+        for _ in 0..times {
+            stats.record("google", Duration::from_millis(latency_ms), success);
+        }
+    }
+
+    #[test]
+    fn averages_towards_the_newest_request() {
+        let stats = ProviderStats::default();
+
+        stats.record("google", Duration::from_millis(100), true);
+        let first = health(&stats, "google");
+        assert_eq!(first.latency_ms, 100.0);
+        assert_eq!(first.error_rate, 0.0);
+
+        stats.record("google", Duration::from_millis(200), false);
+        let second = health(&stats, "google");
+        assert!((second.latency_ms - 120.0).abs() < 1e-9);
+        assert!((second.error_rate - SMOOTHING).abs() < 1e-9);
+        assert_eq!((second.requests, second.errors), (2, 1));
+    }
+
+    #[test]
+    fn waits_for_enough_samples_before_demoting() {
+        let stats = ProviderStats::default();
+
+        record_times(&stats, MIN_SAMPLES - 1, 10, false);
+        assert!(!stats.is_demoted("google", DEADLINE));
+
+        record_times(&stats, 1, 10, false);
+        assert!(stats.is_demoted("google", DEADLINE));
+        assert!(!stats.is_demoted("bing", DEADLINE));
+    }
+
+    #[test]
+    fn demotes_slow_providers_until_they_speed_up() {
+        let stats = ProviderStats::default();
+
+        record_times(&stats, MIN_SAMPLES, 300, true);
+        assert!(stats.is_demoted("google", DEADLINE));
+
+        // 300ms averages down to 250, 210, 178, 152 and then below the deadline
+        record_times(&stats, 4, 50, true);
+        assert!(stats.is_demoted("google", DEADLINE));
+        record_times(&stats, 1, 50, true);
+        assert!(!stats.is_demoted("google", DEADLINE));
+    }
+
+    #[test]
+    fn demotes_failing_providers_until_they_recover() {
+        let stats = ProviderStats::default();
+
+        record_times(&stats, MIN_SAMPLES, 10, false);
+        assert!(health(&stats, "google").demoted);
+
+        // The error rate decays from 1.0 by a fifth per success: 0.8, 0.64, 0.51, 0.41
+        record_times(&stats, 3, 10, true);
+        assert!(health(&stats, "google").demoted);
+        record_times(&stats, 1, 10, true);
+        assert!(!health(&stats, "google").demoted);
+    }
+
+    #[test]
+    fn ranks_demoted_providers_last() {
+        let stats = ProviderStats::default();
+        record_times(&stats, MIN_SAMPLES, 10, false);
+        let providers: Vec<Arc<dyn SuggestionProvider>> = vec![
+            Arc::new(OpenSearchProvider::google()),
+            Arc::new(OpenSearchProvider::bing()),
+            Arc::new(OpenSearchProvider::brave()),
+        ];
+
+        let ranked: Vec<String> = stats
+            .rank(providers, DEADLINE)
+            .iter()
+            .map(|provider| provider.id().to_string())
+            .collect();
+
+        assert_eq!(ranked, ["bing", "brave", "google"]);
+    }
+
+    #[test]
+    fn counts_requests_cancelled_past_the_deadline_as_timeouts() {
+        let stats = ProviderStats::default();
+
+        stats.start("google", DEADLINE).finish(true);
+        drop(stats.start("google", Duration::from_secs(60)));
+        assert_eq!(
+            (
+                health(&stats, "google").requests,
+                health(&stats, "google").errors
+            ),
+            (1, 0)
+        );
+
+        drop(stats.start("google", Duration::ZERO));
+        assert_eq!(
+            (
+                health(&stats, "google").requests,
+                health(&stats, "google").errors
+            ),
+            (2, 1)
+        );
+    }
+}
@@ -1,11 +1,11 @@
 use tauri::Manager;
 use tauri::{
-    menu::{Menu, MenuItem},
+    menu::{CheckMenuItem, Menu, MenuItem},
     tray::TrayIconBuilder,
     App,
 };
 
-use crate::{bangs, logger, search, updater};
+use crate::{bangs, connectivity, logger, search, updater};
 
 pub fn initialize_tray(app: &App) {
     let version = app.package_info().version.to_string();
@@ -39,12 +39,23 @@ pub fn initialize_tray(app: &App) {
     )
     .unwrap();
 
+    let offline_mode_item = CheckMenuItem::with_id(
+        &app.handle().clone(),
+        "offline_mode",
+        "Offline Mode",
+        true,
+        false,
+        None::<&str>,
+    )
+    .unwrap();
+
     let menu = Menu::with_items(
         app,
         &[
             &version_item,
             &open_logs_item,
             &refresh_bangs_item,
+            &offline_mode_item,
             &quit_item,
         ],
     )
@@ -63,6 +74,8 @@ pub fn initialize_tray(app: &App) {
                 logger::info(&format!("Version: {}", env!("CARGO_PKG_VERSION")));
                 if updater::is_dev_mode() {
                     logger::info("Skipping update check in development mode");
+                } else if !connectivity::is_online() {
+                    logger::info("Skipping update check while offline");
                 } else {
                     let app_handle = app.clone();
                     tauri::async_runtime::spawn(async move {
@@ -95,6 +108,10 @@ pub fn initialize_tray(app: &App) {
                     }
                 });
             }
+            "offline_mode" => {
+                let manual_offline = !connectivity::status().manual_offline;
+                connectivity::set_manual_offline(app, manual_offline);
+            }
             _ => {
                 logger::debug(&format!("Menu item {:?} not handled", event.id));
             }
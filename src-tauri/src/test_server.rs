@@ -0,0 +1,80 @@
+//! A minimal HTTP server for tests of code that fetches from the web
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+/// A canned response for requests to a path, ignoring the query string
+#[derive(Clone)]
+pub(crate) struct Route {
+    path: &'static str,
+    content_type: &'static str,
+    body: String,
+    delay: Duration,
+}
+
+impl Route {
+    pub(crate) fn new(path: &'static str, content_type: &'static str, body: &str) -> Self {
+        Self {
+            path,
+            content_type,
+            body: body.to_string(),
+            delay: Duration::ZERO,
+        }
+    }
+
+    /// Waits before responding, for testing timeouts and slow services
+    pub(crate) fn delayed(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+/// Serves the routes on a local port until the tests exit, returning the base URL.
+/// Other paths get a 404.
+pub(crate) fn serve(routes: Vec<Route>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let routes = routes.clone();
+            thread::spawn(move || {
+                let mut reader = BufReader::new(&stream);
+                let mut request_line = String::new();
+                if reader.read_line(&mut request_line).is_err() {
+                    return;
+                }
+
+                // Headers are read up to the blank line and ignored
+                let mut header = String::new();
+                while reader.read_line(&mut header).is_ok_and(|read| read > 2) {
+                    header.clear();
+                }
+
+                let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+                let path = target.split('?').next().unwrap_or(target);
+
+                let response = match routes.iter().find(|route| route.path == path) {
+                    Some(route) => {
+                        thread::sleep(route.delay);
+                        format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                            route.content_type,
+                            route.body.len(),
+                            route.body
+                        )
+                    }
+                    None => {
+                        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                            .to_string()
+                    }
+                };
+                let _ = (&stream).write_all(response.as_bytes());
+            });
+        }
+    });
+
+    format!("http://{}", address)
+}
@@ -1,16 +1,25 @@
 use tauri::AppHandle;
 use tauri_plugin_updater::UpdaterExt;
 
-use crate::logger;
+use crate::{connectivity, http, logger};
 
 pub fn is_dev_mode() -> bool {
     cfg!(dev)
 }
 
-pub async fn check_for_updates(app: AppHandle) -> tauri_plugin_updater::Result<()> {
+pub async fn check_for_updates(app: AppHandle) -> Result<(), String> {
     logger::info("Checking for updates...");
 
-    match app.updater()?.check().await {
+    // The updater makes its own requests through the same proxy and certificates, but
+    // without the request timeout, which would cut off the download
+    let options = http::client_options()?;
+    let updater = app
+        .updater_builder()
+        .configure_client(move |client| options.apply_connection(client))
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    match updater.check().await {
         Ok(Some(update)) => {
             logger::info(&format!(
                 "Update found: {} (current: {})",
@@ -28,9 +37,23 @@ pub async fn check_for_updates(app: AppHandle) -> tauri_plugin_updater::Result<(
         }
         Err(e) => {
             logger::error(&format!("Error checking for updates: {}", e));
-            return Err(e);
+            return Err(e.to_string());
         }
     }
 
     Ok(())
 }
+
+#[tauri::command]
+pub async fn check_updates(app_handle: AppHandle) -> Result<(), String> {
+    if is_dev_mode() {
+        return Err("Updates aren't available in development mode".to_string());
+    }
+    if !connectivity::is_online() {
+        return Err("Can't check for updates while offline".to_string());
+    }
+
+    check_for_updates(app_handle)
+        .await
+        .map_err(|e| format!("Update check failed: {}", e))
+}
@@ -1,10 +1,10 @@
 import { invoke } from '@tauri-apps/api/core';
+import type { Suggestion } from './searchService';
 
 export const getSearchSuggestions = async (query: string, limit: number = 8) => {
   if (query.length === 0) return [];
 
-  const suggestions = await invoke<string[]>('get_search_suggestions', { query });
-  return suggestions.slice(0, limit);
+  return await invoke<Suggestion[]>('get_search_suggestions', { query, limit });
 };
 
 export const getAvailableBangs = async () => {
@@ -12,6 +12,88 @@ export const getAvailableBangs = async () => {
   return availableBangs;
 };
 
+export interface ConnectivityStatus {
+  online: boolean;
+  manual_offline: boolean;
+}
+
+export const getConnectivityStatus = async () => {
+  return await invoke<ConnectivityStatus>('get_connectivity_status');
+};
+
+export interface ConfigError {
+  file: string;
+  message: string;
+}
+
+export const getConfigErrors = async () => {
+  return await invoke<ConfigError[]>('get_config_errors');
+};
+
+export interface RecoveredFile {
+  file: string;
+  backup: string;
+  error: string;
+  recovered_at: string;
+}
+
+export const getRecoveredFiles = async () => {
+  return await invoke<RecoveredFile[]>('get_recovered_files');
+};
+
+export const dismissRecoveredFiles = async () => {
+  await invoke('dismiss_recovered_files');
+};
+
+export type SearchEngine =
+  | 'google'
+  | 'duckduckgo'
+  | 'bing'
+  | 'brave'
+  | { custom: { url: string } };
+
+export interface Settings {
+  hotkey: string;
+  run_at_startup: boolean;
+  default_engine: SearchEngine;
+  bang_cache_ttl_days: number;
+}
+
+export interface KeywordConfig {
+  enabled: boolean;
+  keywords: string[];
+}
+
+// The other groups of settings are passed through as stored by the backend
+export type SettingsSection =
+  | { section: 'general'; value: Settings }
+  | { section: 'locale'; value: unknown }
+  | { section: 'suggestions'; value: unknown }
+  | { section: 'network'; value: unknown }
+  | { section: 'history'; value: unknown }
+  | { section: 'detectors'; value: unknown }
+  | { section: 'keywords'; value: KeywordConfig }
+  | { section: 'log_privacy'; value: unknown };
+
+export interface AllSettings {
+  general: Settings;
+  locale: unknown;
+  suggestions: unknown;
+  network: unknown;
+  history: unknown;
+  detectors: unknown;
+  keywords: KeywordConfig;
+  log_privacy: unknown;
+}
+
+export const getSettings = async () => {
+  return await invoke<AllSettings>('get_settings');
+};
+
+export const updateSettings = async (change: SettingsSection) => {
+  await invoke('update_settings', { change });
+};
+
 export const executeSearch = async (query: string) => {
   if (query.length === 0) return;
   await invoke('search', { query });
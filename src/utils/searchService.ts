@@ -1,8 +1,25 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+
+export type SuggestionKind = 'detected' | 'url' | 'path' | 'bang' | 'history' | 'remote';
+
+export interface Suggestion {
+  kind: SuggestionKind;
+  display: string;
+  completion: string;
+  url: string | null;
+  icon: string;
+  score: number;
+}
+
+interface SuggestionUpdate {
+  seq: number;
+  suggestions: Suggestion[];
+}
 
 export class SearchService {
   private bangs: [string, string][] = [];
-  private currentSuggestions: string[] = [];
+  private suggestionSeq = 0;
 
   public async getSearchResults(query: string): Promise<string[]> {
     if (query.length === 0) return [];
@@ -16,41 +33,48 @@ export class SearchService {
     return this.bangs;
   }
 
-  public async getSearchSuggestions(query: string, limit: number = 8): Promise<string[]> {
+  /**
+   * Resolves to null when a newer request was made before this one finished,
+   * in which case the result should be ignored
+   */
+  public async getSearchSuggestions(
+    query: string,
+    limit: number = 8
+  ): Promise<Suggestion[] | null> {
     if (query.length === 0) return [];
 
-    // If no bang, fetch suggestions for full query
-    if (!query.includes('!')) {
-      this.currentSuggestions = await invoke<string[]>('get_search_suggestions', { query });
-      return this.currentSuggestions.slice(0, limit);
-    }
-
-    const bangs = await this.getBangs();
+    const seq = ++this.suggestionSeq;
 
-    // If starts with bang, show suggestions from available bangs
-    if (query.startsWith('!')) {
-      const bang = query.slice(1);
+    // The backend merges bang completions, history, detected input and remote suggestions
+    const suggestions = await invoke<Suggestion[] | null>('get_search_suggestions', {
+      query,
+      limit,
+      seq,
+    });
 
-      // Get unique bangs only - keep only the first occurrence of each [0] value
-      const uniqueBangs: [string, string][] = [];
-      const seenPrefixes = new Set<string>();
+    return seq === this.suggestionSeq ? suggestions : null;
+  }
 
-      for (const b of bangs) {
-        if (!seenPrefixes.has(b[0])) {
-          uniqueBangs.push(b);
-          seenPrefixes.add(b[0]);
-        }
+  /**
+   * Calls back with the full suggestions once providers that missed the deadline
+   * have answered, as long as no newer request has been made since
+   */
+  public async onLateSuggestions(
+    callback: (suggestions: Suggestion[]) => void
+  ): Promise<UnlistenFn> {
+    return await listen<SuggestionUpdate>('suggestions-updated', (event) => {
+      if (event.payload.seq === this.suggestionSeq) {
+        callback(event.payload.suggestions);
       }
+    });
+  }
 
-      const possibleBangs = uniqueBangs.filter((b) => b[0].startsWith(bang));
-      const sortedBangs = possibleBangs.sort((a, b) => a[0].length - b[0].length);
-
-      const suggestions = sortedBangs.map((b) => b[1]).slice(0, limit);
-      const suggestionStrings = suggestions.map((s) => `!${bang} (${s})`);
-      return suggestionStrings;
-    }
-
-    // If contains a bang, but not at the start, just return current suggestions
-    return this.currentSuggestions.slice(0, limit);
+  /**
+   * Makes any suggestion requests still in flight resolve to null and stops
+   * their provider requests in the backend
+   */
+  public async cancelSuggestions() {
+    const seq = ++this.suggestionSeq;
+    await invoke('cancel_suggestions', { seq });
   }
 }